@@ -0,0 +1,353 @@
+// Headless load-testing client. Spawns a number of simulated players that each open a websocket
+// to the game server, do the same salutations/welcome/ack handshake as the wasm client and then
+// stream synthetic cursorPosition messages until the run is over.
+//
+// cargo run --release --bin bot -- --clients 50 --rate 60 --duration 30
+
+use client::game_bits::protocol::{self, IncomingMessage, OutgoingMessage};
+
+use std::{
+    env,
+    net::TcpStream,
+    process,
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tungstenite::{http::Request, Message, WebSocket};
+
+struct BotConfig {
+    url: String,
+    // Sent as the Origin header, the server only accepts sockets from the game's page.
+    origin: String,
+    clients: usize,
+    // Clients started per second.
+    spawn_rate: f64,
+    // cursorPosition messages per second, per client.
+    message_rate: f64,
+    duration: Duration,
+    // How long to wait for a welcome before giving up on a client.
+    handshake_timeout: Duration,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            url: "ws://localhost:5000/websocket".to_string(),
+            origin: "http://localhost:4000".to_string(),
+            clients: 10,
+            spawn_rate: 10.0,
+            message_rate: 30.0,
+            duration: Duration::from_secs(10),
+            handshake_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+const USAGE: &str = "usage: bot [--url URL] [--origin ORIGIN] [--clients N] \
+[--spawn-rate PER_SEC] [--rate MSGS_PER_SEC] [--duration SECS] [--handshake-timeout SECS]";
+
+impl BotConfig {
+    fn from_args() -> Result<Self, String> {
+        let mut config = BotConfig::default();
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            if arg == "--help" || arg == "-h" {
+                return Err(USAGE.to_string());
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", arg))?;
+
+            match arg.as_str() {
+                "--url" => config.url = value,
+                "--origin" => config.origin = value,
+                "--clients" => config.clients = parse_value(&arg, &value)?,
+                "--spawn-rate" => config.spawn_rate = parse_positive(&arg, &value)?,
+                "--rate" => config.message_rate = parse_positive(&arg, &value)?,
+                "--duration" => {
+                    config.duration = Duration::from_secs_f64(parse_positive(&arg, &value)?)
+                }
+                "--handshake-timeout" => {
+                    config.handshake_timeout =
+                        Duration::from_secs_f64(parse_positive(&arg, &value)?)
+                }
+                _ => return Err(format!("unknown argument {}\n{}", arg, USAGE)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("bad value for {}: {}", arg, value))
+}
+
+// Rates and durations, these get divided by or turned into a Duration, which panics on negative,
+// NaN and infinite values.
+fn parse_positive(arg: &str, value: &str) -> Result<f64, String> {
+    let number: f64 = parse_value(arg, value)?;
+    if number.is_finite() && number > 0.0 {
+        Ok(number)
+    } else {
+        Err(format!(
+            "{} must be a number greater than zero, not {}\n{}",
+            arg, value, USAGE
+        ))
+    }
+}
+
+/// What a single simulated client saw during the run.
+#[derive(Default)]
+struct ClientReport {
+    connect_time: Option<Duration>,
+    // Time from sending salutations to receiving the welcome, the only real round trip the protocol
+    // has. The server never answers cursorPosition or ack, so there is no latency to measure there.
+    handshake_time: Option<Duration>,
+    messages_sent: u64,
+    // Time spent inside write_message. This is not latency, only a rough measure of back pressure
+    // once the socket's send buffer fills up.
+    write_times: Vec<Duration>,
+    errors: Vec<String>,
+}
+
+fn run_client(index: usize, config: &BotConfig, deadline: Instant) -> ClientReport {
+    let mut report = ClientReport::default();
+
+    let started = Instant::now();
+    let mut socket = match connect(config) {
+        Ok(socket) => socket,
+        Err(err) => {
+            report.errors.push(format!("connect: {}", err));
+            return report;
+        }
+    };
+    report.connect_time = Some(started.elapsed());
+
+    let client_id = match handshake(&mut socket, config.handshake_timeout) {
+        Ok((client_id, handshake_time)) => {
+            report.handshake_time = Some(handshake_time);
+            client_id
+        }
+        Err(err) => {
+            report.errors.push(format!("handshake: {}", err));
+            return report;
+        }
+    };
+
+    let interval = Duration::from_secs_f64(1.0 / config.message_rate);
+    let mut next_send = Instant::now();
+    // Each bot moves its cursor around a circle of its own, so the server gets distinct values.
+    let radius = 100.0 + index as f64;
+    let mut angle = 0.0f64;
+
+    while next_send < deadline {
+        let now = Instant::now();
+        if now < next_send {
+            thread::sleep(next_send - now);
+        }
+
+        angle += 0.05;
+        let message = OutgoingMessage::CursorPosition {
+            client_id,
+            x: radius * angle.cos(),
+            y: radius * angle.sin(),
+        };
+
+        let write_started = Instant::now();
+        match socket.write_message(Message::Text(message.to_json())) {
+            Ok(_) => {
+                report.write_times.push(write_started.elapsed());
+                report.messages_sent += 1;
+            }
+            Err(err) => {
+                report.errors.push(format!("send: {}", err));
+                break;
+            }
+        }
+
+        next_send += interval;
+    }
+
+    if let Err(err) = socket.close(None) {
+        report.errors.push(format!("close: {}", err));
+    }
+
+    report
+}
+
+fn connect(config: &BotConfig) -> Result<WebSocket<TcpStream>, String> {
+    let request = Request::builder()
+        .uri(config.url.as_str())
+        .header("Origin", config.origin.as_str())
+        .body(())
+        .map_err(|err| err.to_string())?;
+    let (socket, _) = tungstenite::connect(request).map_err(|err| err.to_string())?;
+    Ok(socket)
+}
+
+fn handshake(
+    socket: &mut WebSocket<TcpStream>,
+    timeout: Duration,
+) -> Result<(u32, Duration), String> {
+    socket
+        .get_mut()
+        .set_read_timeout(Some(timeout))
+        .map_err(|err| err.to_string())?;
+
+    let started = Instant::now();
+    socket
+        .write_message(Message::Text(OutgoingMessage::Salutations.to_json()))
+        .map_err(|err| err.to_string())?;
+
+    let welcome = loop {
+        match socket.read_message().map_err(|err| err.to_string())? {
+            Message::Text(text) => {
+                let message = IncomingMessage::from_json(&text).map_err(|err| err.to_string())?;
+                if message.is_welcome() {
+                    break message;
+                }
+            }
+            Message::Close(_) => return Err("server closed the connection".to_string()),
+            _ => (),
+        }
+    };
+    let handshake_time = started.elapsed();
+
+    if let Some(reply) = protocol::reply_to(&welcome) {
+        socket
+            .write_message(Message::Text(reply.to_json()))
+            .map_err(|err| err.to_string())?;
+    }
+
+    Ok((welcome.client_id, handshake_time))
+}
+
+struct TimingSummary {
+    count: usize,
+    min: Duration,
+    mean: Duration,
+    p50: Duration,
+    p95: Duration,
+    max: Duration,
+}
+
+impl TimingSummary {
+    fn new(mut samples: Vec<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+
+        let count = samples.len();
+        let percentile = |p: f64| samples[((count - 1) as f64 * p).round() as usize];
+        let total: Duration = samples.iter().sum();
+
+        Some(Self {
+            count,
+            min: samples[0],
+            mean: total / count as u32,
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            max: samples[count - 1],
+        })
+    }
+
+    fn print(&self, name: &str) {
+        println!(
+            "{:<10} n={:<8} min={:>9.3?} mean={:>9.3?} p50={:>9.3?} p95={:>9.3?} max={:>9.3?}",
+            name, self.count, self.min, self.mean, self.p50, self.p95, self.max
+        );
+    }
+}
+
+fn print_summary(config: &BotConfig, reports: &[ClientReport], elapsed: Duration) {
+    let connected = reports.iter().filter(|r| r.connect_time.is_some()).count();
+    let welcomed = reports.iter().filter(|r| r.handshake_time.is_some()).count();
+    let messages: u64 = reports.iter().map(|r| r.messages_sent).sum();
+
+    println!(
+        "{} clients, {} connected, {} completed the handshake",
+        config.clients, connected, welcomed
+    );
+    println!(
+        "{} cursorPosition messages in {:.1?} ({:.1} msg/s total)",
+        messages,
+        elapsed,
+        messages as f64 / elapsed.as_secs_f64()
+    );
+
+    let summaries = vec![
+        ("connect", reports.iter().filter_map(|r| r.connect_time).collect()),
+        ("handshake", reports.iter().filter_map(|r| r.handshake_time).collect()),
+        ("write", reports.iter().flat_map(|r| r.write_times.iter().cloned()).collect()),
+    ];
+    for (name, samples) in summaries {
+        if let Some(summary) = TimingSummary::new(samples) {
+            summary.print(name);
+        }
+    }
+
+    let errors: Vec<&String> = reports.iter().flat_map(|r| r.errors.iter()).collect();
+    println!("{} errors", errors.len());
+    for error in errors.iter().take(20) {
+        println!("  {}", error);
+    }
+    if errors.len() > 20 {
+        println!("  ...");
+    }
+}
+
+fn main() {
+    let config = match BotConfig::from_args() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    println!(
+        "starting {} clients against {} at {} clients/s, {} msg/s each, for {:?}",
+        config.clients, config.url, config.spawn_rate, config.message_rate, config.duration
+    );
+
+    let started = Instant::now();
+    let spawn_interval = Duration::from_secs_f64(1.0 / config.spawn_rate);
+    let config = Arc::new(config);
+
+    let handles: Vec<_> = (0..config.clients)
+        .map(|index| {
+            if index > 0 {
+                thread::sleep(spawn_interval);
+            }
+            // Every client streams for the full duration, counted from its own start.
+            let deadline = Instant::now() + config.duration;
+            let config = config.clone();
+            thread::spawn(move || run_client(index, &config, deadline))
+        })
+        .collect();
+
+    let reports: Vec<ClientReport> = handles
+        .into_iter()
+        .map(|handle| {
+            handle.join().unwrap_or_else(|_| ClientReport {
+                errors: vec!["client thread panicked".to_string()],
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    print_summary(&config, &reports, started.elapsed());
+
+    if reports.iter().any(|r| !r.errors.is_empty()) {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,250 @@
+// Native desktop front end, runs the same game as the browser build without wasm-pack so it can
+// be started under a regular debugger or profiler.
+//
+// cargo run --bin desktop -- --server ws://localhost:5000/websocket
+
+use client::game_bits::{
+    app::{self, App, AppConfig},
+    assets::MANIFEST_PATH,
+    camera::LookSettings,
+    gamepad::GamepadSettings,
+    native_websocket::NativeWebsocket,
+    net::Transport,
+    platform::{BoxedFuture, Platform},
+    recording::InputLog,
+};
+
+use rg3d::{
+    core::futures::executor::block_on,
+    event_loop::EventLoop,
+    window::{Fullscreen, Window},
+};
+
+use std::{
+    cell::Cell,
+    env, fs,
+    path::{Path, PathBuf},
+    process, thread,
+};
+
+const USAGE: &str = "usage: desktop [--server URL] [--origin ORIGIN] [--assets DIR] \
+                     [--level PATH] [--dev] [--sensitivity DEGREES_PER_PIXEL] [--invert-y] \
+                     [--record FILE] [--replay FILE] [--exit-after-replay] [--speed FACTOR]";
+
+struct DesktopConfig {
+    server: String,
+    // Sent as the Origin header, the server only accepts sockets from the game's page.
+    origin: String,
+    // A level id from the manifest, e.g. levels.withcamera, or an .rgs relative to the asset
+    // root.
+    level: Option<String>,
+    // Directory that contains assets/, all asset paths in the game are relative to it.
+    asset_root: PathBuf,
+    // Reload changed assets while running.
+    dev: bool,
+    look: LookSettings,
+    // Where to save the input recording, if recording.
+    record: Option<PathBuf>,
+    replay: Option<InputLog>,
+    exit_after_replay: bool,
+    // Game time per real time, e.g. to get through replays faster.
+    speed: f32,
+}
+
+impl DesktopConfig {
+    fn from_args() -> Result<Self, String> {
+        let mut config = DesktopConfig {
+            server: "ws://localhost:5000/websocket".to_string(),
+            origin: "http://localhost:4000".to_string(),
+            level: None,
+            asset_root: default_asset_root(),
+            dev: false,
+            look: LookSettings::default(),
+            record: None,
+            replay: None,
+            exit_after_replay: false,
+            speed: 1.0,
+        };
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--server" => config.server = args.next().ok_or(USAGE)?,
+                "--origin" => config.origin = args.next().ok_or(USAGE)?,
+                "--assets" => config.asset_root = args.next().ok_or(USAGE)?.into(),
+                "--level" => config.level = Some(args.next().ok_or(USAGE)?),
+                "--dev" => config.dev = true,
+                "--sensitivity" => {
+                    config.look.sensitivity =
+                        args.next().and_then(|arg| arg.parse().ok()).ok_or(USAGE)?
+                }
+                "--invert-y" => config.look.invert_y = true,
+                // Relative to where we were started, not the asset root we move to.
+                "--record" => {
+                    let path = args.next().ok_or(USAGE)?;
+                    config.record = Some(
+                        env::current_dir()
+                            .map_err(|err| err.to_string())?
+                            .join(path),
+                    )
+                }
+                "--replay" => config.replay = Some(read_recording(&args.next().ok_or(USAGE)?)?),
+                "--exit-after-replay" => config.exit_after_replay = true,
+                "--speed" => {
+                    config.speed = args.next().and_then(|arg| arg.parse().ok()).ok_or(USAGE)?
+                }
+                _ => return Err(USAGE.to_string()),
+            }
+        }
+
+        if !config.asset_root.join("assets").is_dir() {
+            return Err(format!(
+                "no assets directory in {}",
+                config.asset_root.display()
+            ));
+        }
+
+        Ok(config)
+    }
+}
+
+fn read_recording(path: &str) -> Result<InputLog, String> {
+    let json = fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+    InputLog::from_json(&json).map_err(|err| format!("bad recording {}: {}", path, err))
+}
+
+// Prefer the working directory, fall back to the crate directory so `cargo run` works from
+// anywhere in the repo.
+fn default_asset_root() -> PathBuf {
+    if Path::new("assets").is_dir() {
+        PathBuf::from(".")
+    } else {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    }
+}
+
+// Settings are saved next to assets/, one file each.
+const SETTINGS_DIR: &str = "settings";
+
+fn setting_path(key: &str) -> PathBuf {
+    Path::new(SETTINGS_DIR).join(key).with_extension("json")
+}
+
+struct NativePlatform {
+    // winit can grab the cursor but can't tell whether it did, or whether the window has focus.
+    pointer_locked: Cell<bool>,
+    focused: Cell<bool>,
+    origin: String,
+    recording_path: Option<PathBuf>,
+}
+
+impl Platform for NativePlatform {
+    // There is no browser executor here, futures get a thread of their own.
+    fn spawn(&self, future: BoxedFuture) {
+        thread::spawn(move || block_on(future));
+    }
+
+    fn connect(&self, url: &str) -> Box<dyn Transport> {
+        Box::new(NativeWebsocket::new(url, &self.origin))
+    }
+
+    fn load_setting(&self, key: &str) -> Option<String> {
+        fs::read_to_string(setting_path(key)).ok()
+    }
+
+    fn save_setting(&self, key: &str, value: &str) {
+        let result =
+            fs::create_dir_all(SETTINGS_DIR).and_then(|_| fs::write(setting_path(key), value));
+        if let Err(err) = result {
+            eprintln!("can't save setting {}: {}", key, err);
+        }
+    }
+
+    fn set_pointer_lock(&self, window: &Window, locked: bool) {
+        // Taking the cursor away from whatever the player switched to, the next click on our
+        // window asks again.
+        if locked && !self.focused.get() {
+            return;
+        }
+
+        match window.set_cursor_grab(locked) {
+            Ok(()) => {
+                window.set_cursor_visible(!locked);
+                self.pointer_locked.set(locked);
+            }
+            Err(err) => eprintln!("can't grab the cursor: {}", err),
+        }
+    }
+
+    fn is_pointer_locked(&self) -> bool {
+        self.pointer_locked.get()
+    }
+
+    fn set_focused(&self, focused: bool) {
+        self.focused.set(focused);
+    }
+
+    fn set_fullscreen(&self, window: &Window, fullscreen: bool) {
+        window.set_fullscreen(if fullscreen {
+            Some(Fullscreen::Borderless(None))
+        } else {
+            None
+        });
+    }
+
+    fn is_fullscreen(&self, window: &Window) -> bool {
+        window.fullscreen().is_some()
+    }
+
+    fn save_recording(&self, json: &str) {
+        if let Some(path) = self.recording_path.as_ref() {
+            match fs::write(path, json) {
+                Ok(()) => println!("input recording saved to {}", path.display()),
+                Err(err) => eprintln!("can't save input recording: {}", err),
+            }
+        }
+    }
+}
+
+fn main() {
+    let config = match DesktopConfig::from_args() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    // The resource manager loads straight from the filesystem on native targets, using the same
+    // relative paths the browser fetches from the http server.
+    env::set_current_dir(&config.asset_root).unwrap();
+
+    let event_loop = EventLoop::new();
+    let engine = app::create_engine(&event_loop);
+    let app = App::new(
+        engine,
+        Box::new(NativePlatform {
+            pointer_locked: Cell::new(false),
+            focused: Cell::new(true),
+            origin: config.origin.clone(),
+            recording_path: config.record.clone(),
+        }),
+        AppConfig {
+            server_url: config.server,
+            level: config.level,
+            manifest_url: MANIFEST_PATH.to_string(),
+            // Files are read straight from disk, there is no cache to bust.
+            hashed_asset_urls: false,
+            hot_reload: config.dev,
+            look: config.look,
+            // winit doesn't do gamepads, only the browser build has them.
+            gamepad: GamepadSettings::default(),
+            record: config.record.is_some(),
+            replay: config.replay,
+            exit_after_replay: config.exit_after_replay,
+            time_scale: config.speed,
+        },
+    );
+
+    app::run(event_loop, app);
+}
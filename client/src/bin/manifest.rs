@@ -0,0 +1,74 @@
+// Refreshes the content hashes in assets/manifest.json, run it from client/ after adding or
+// changing an asset.
+//
+// cargo run --bin manifest
+// cargo run --bin manifest -- --check    only reports stale hashes, exits with 1 if there are any
+// cargo run --bin manifest -- --watch    keeps refreshing, for clients running in dev mode
+
+use client::game_bits::assets::{content_hash, Manifest, MANIFEST_PATH};
+
+use std::{env, fs, process, thread, time::Duration};
+
+const USAGE: &str = "usage: manifest [--check | --watch]";
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+fn run(check: bool) -> Result<bool, String> {
+    let json = fs::read_to_string(MANIFEST_PATH)
+        .map_err(|err| format!("can't read {}: {}", MANIFEST_PATH, err))?;
+    let mut manifest = Manifest::from_json(&json)?;
+
+    let mut stale = false;
+    for (id, entry) in manifest.assets.iter_mut() {
+        let data =
+            fs::read(&entry.path).map_err(|err| format!("{} ({}): {}", id, entry.path, err))?;
+        let hash = content_hash(&data);
+        if hash != entry.hash {
+            println!("{}: {} -> {}", id, entry.path, hash);
+            entry.hash = hash;
+            stale = true;
+        }
+    }
+
+    if stale && !check {
+        fs::write(MANIFEST_PATH, manifest.to_json() + "\n")
+            .map_err(|err| format!("can't write {}: {}", MANIFEST_PATH, err))?;
+    }
+
+    Ok(stale)
+}
+
+// Hashing everything twice a second is fine for the handful of assets we have.
+fn watch() -> ! {
+    loop {
+        // Assets get saved half written now and then, the next round picks them up.
+        if let Err(err) = run(false) {
+            eprintln!("{}", err);
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+fn main() {
+    let check = match env::args().nth(1).as_deref() {
+        None => false,
+        Some("--check") => true,
+        Some("--watch") => watch(),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(check) {
+        Ok(stale) => {
+            if stale && check {
+                process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+// Platform independent part of the client: engine setup and the main loop. The front ends
+// (web.rs for the browser, bin/desktop.rs for native) only do what needs their platform and
+// then hand over to `run`.
+
+use rg3d::{
+    core::color::Color,
+    engine::{resource_manager::TextureImportOptions, Engine},
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    gui::node::StubNode,
+    resource::texture::CompressionOptions,
+    utils::translate_event,
+    window::WindowBuilder,
+};
+
+use std::sync::Arc;
+
+use super::{
+    assets::Manifest,
+    camera::LookSettings,
+    clock::GameClock,
+    gamepad::GamepadSettings,
+    game_scene::GameScene,
+    hot_reload::HotReload,
+    input::{Action, Input},
+    key_bindings::KeyBindings,
+    net::Transport,
+    platform::Platform,
+    pointer_lock::PointerLock,
+    recording::{InputLog, RecordedEvent, Recorder, Replay},
+    scene_manager::SceneManager,
+    states::{LoadingState, StateStack},
+    step::{run_steps, FixedStep},
+    touch::TouchEvent,
+};
+
+// Create our own engine type aliases. These specializations are needed, because the engine
+// provides a way to extend UI with custom nodes and messages.
+pub type GameEngine = Engine<(), StubNode>;
+pub type UiNode = rg3d::gui::node::UINode<(), StubNode>;
+pub type UiMessage = rg3d::gui::message::UiMessage<(), StubNode>;
+pub type BuildContext<'a> = rg3d::gui::BuildContext<'a, (), StubNode>;
+
+// Our game logic will be updated at 60 Hz rate.
+pub const TIMESTEP: f32 = 1.0 / 60.0;
+
+// Seconds to fade out and back in when changing levels.
+const LEVEL_FADE: f32 = 0.5;
+
+pub struct ScreenSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Creates the main window and the engine that renders into it.
+pub fn create_engine(event_loop: &EventLoop<()>) -> GameEngine {
+    // Configure main window first.
+    let window_builder = WindowBuilder::new().with_title("Gorust!");
+
+    // Finally create an instance of the engine.
+    let mut engine = GameEngine::new(window_builder, event_loop, true).unwrap();
+    engine.renderer.set_backbuffer_clear_color(Color::opaque(150, 150, 255));
+
+    // Configure resource manager.
+    engine.resource_manager.state().set_textures_import_options(
+        TextureImportOptions::default().with_compression(CompressionOptions::NoCompression),
+    );
+    engine
+        .resource_manager
+        .state()
+        .set_textures_path("assets/textures");
+
+    engine
+}
+
+/// Startup options, filled in by the front end.
+pub struct AppConfig {
+    pub server_url: String,
+    /// Level file or editor scene, as a manifest id or a path. None for the built-in test
+    /// level.
+    pub level: Option<String>,
+    /// Where to fetch the asset manifest from.
+    pub manifest_url: String,
+    /// Put content hashes into asset urls, so the browser doesn't use stale cached files.
+    pub hashed_asset_urls: bool,
+    /// Development mode, assets that change on the server get reloaded in the running game.
+    pub hot_reload: bool,
+    /// Mouse sensitivity and invert-Y for looking around.
+    pub look: LookSettings,
+    pub gamepad: GamepadSettings,
+    /// Log input for `Platform::save_recording`, on exit and on `Action::SaveRecording`.
+    pub record: bool,
+    /// Play this back instead of live input, which takes over again once it is done.
+    pub replay: Option<InputLog>,
+    /// Quit when the replay is done, for regression runs.
+    pub exit_after_replay: bool,
+    /// Game time per real time, 1 for normal speed.
+    pub time_scale: f32,
+}
+
+/// Everything the game states share, it is passed into each of their callbacks.
+pub struct GameContext {
+    pub engine: GameEngine,
+    pub platform: Box<dyn Platform>,
+    pub clock: GameClock,
+    pub config: AppConfig,
+    pub input: Input,
+    pub pointer_lock: PointerLock,
+    /// Empty until the loading screen got it.
+    pub manifest: Arc<Manifest>,
+    /// Set while connecting or connected to the server.
+    pub connection: Option<Box<dyn Transport>>,
+    /// The level, once it finished loading.
+    pub scene: Option<GameScene>,
+    /// Manifest id or path of the level in `scene`.
+    pub level: String,
+    /// Set this to change levels, the scene manager takes it from here.
+    pub next_level: Option<String>,
+    pub screen_size: ScreenSize,
+}
+
+/// Game state that lives for as long as the main loop runs.
+pub struct App {
+    pub ctx: GameContext,
+    states: StateStack,
+    scene_manager: SceneManager,
+    hot_reload: Option<HotReload>,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    // Set when a regression replay is done, the main loop quits after the frame.
+    exit_requested: bool,
+}
+
+impl App {
+    pub fn new(engine: GameEngine, platform: Box<dyn Platform>, config: AppConfig) -> Self {
+        let screen_size = ScreenSize {
+            width: engine.get_window().inner_size().width,
+            height: engine.get_window().inner_size().height,
+        };
+
+        let mut input = Input::new();
+        input.bindings = KeyBindings::load(&*platform);
+        input.gamepad_settings = config.gamepad;
+        input
+            .touch
+            .set_screen_size(screen_size.width, screen_size.height);
+
+        let mut clock = GameClock::new(TIMESTEP);
+        clock.set_time_scale(config.time_scale);
+
+        let mut ctx = GameContext {
+            engine,
+            platform,
+            clock,
+            config,
+            input,
+            pointer_lock: PointerLock::default(),
+            manifest: Arc::new(Manifest::default()),
+            connection: None,
+            scene: None,
+            level: String::new(),
+            next_level: None,
+            screen_size,
+        };
+
+        let recorder = if ctx.config.record {
+            Some(Recorder::default())
+        } else {
+            None
+        };
+        let replay = ctx.config.replay.take().map(Replay::new);
+
+        let mut states = StateStack::new();
+        states.push(&mut ctx, Box::new(LoadingState::new()));
+
+        let hot_reload = if ctx.config.hot_reload {
+            Some(HotReload::new(&mut ctx))
+        } else {
+            None
+        };
+
+        Self {
+            ctx,
+            states,
+            scene_manager: SceneManager::new(LEVEL_FADE),
+            hot_reload,
+            recorder,
+            replay,
+            exit_requested: false,
+        }
+    }
+
+    // Input events go through here, live or replayed.
+    fn dispatch_event(&mut self, event: &Event<()>) {
+        // It is very important to "feed" user interface (UI) with events coming
+        // from main window, otherwise UI won't respond to mouse, keyboard, or any
+        // other event.
+        if let Event::WindowEvent { event, .. } = event {
+            if let Some(os_event) = translate_event(event) {
+                self.ctx.engine.user_interface.process_os_event(&os_event);
+            }
+        }
+
+        self.ctx.input.handle_event(event);
+
+        // Whatever the current state wants to react to, e.g. mouse motion while playing.
+        self.states.handle_event(&mut self.ctx, event);
+    }
+
+    fn handle_touch(&mut self, touch: TouchEvent) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(self.ctx.clock.ticks(), RecordedEvent::from_touch(touch));
+        }
+        self.ctx.input.handle_touch(touch);
+    }
+
+    // Feeds in what the replay has for step `tick`.
+    fn replay_step(&mut self, tick: u64) {
+        let events = match self.replay.as_mut() {
+            Some(replay) => replay.take_events(tick),
+            None => return,
+        };
+
+        for event in events {
+            if let Some(touch) = event.to_touch() {
+                self.ctx.input.handle_touch(touch);
+            } else if let Some(event) = event.to_event() {
+                self.dispatch_event(&event);
+            }
+        }
+    }
+
+    fn save_recording(&self) {
+        if let Some(recorder) = self.recorder.as_ref() {
+            self.ctx.platform.save_recording(&recorder.log().to_json());
+        }
+    }
+
+    fn on_resize(&mut self, width: u32, height: u32) {
+        // It is very important to handle Resized event from window, because
+        // renderer knows nothing about window size - it must be notified
+        // directly when window size has changed.
+        self.ctx.screen_size.width = width;
+        self.ctx.screen_size.height = height;
+        self.ctx.input.touch.set_screen_size(width, height);
+        self.ctx.engine.renderer.set_frame_size((width, height)).unwrap();
+    }
+}
+
+impl FixedStep for App {
+    fn feed_input(&mut self, tick: u64) {
+        self.replay_step(tick);
+        if self.replay.as_ref().map_or(false, Replay::is_finished) {
+            self.replay = None;
+            if self.ctx.config.exit_after_replay {
+                self.exit_requested = true;
+            }
+        }
+
+        // Polled state comes from the replay too, while there is one.
+        let (gamepads, pointer_locked, fullscreen) = match self.replay.as_ref() {
+            Some(replay) => (
+                replay.gamepads(),
+                replay.is_pointer_locked(),
+                replay.is_fullscreen(),
+            ),
+            None => (
+                self.ctx.platform.gamepads(),
+                self.ctx.platform.is_pointer_locked(),
+                self.ctx
+                    .platform
+                    .is_fullscreen(self.ctx.engine.get_window()),
+            ),
+        };
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record_gamepads(tick, &gamepads);
+            recorder.record_pointer_lock(tick, pointer_locked, fullscreen);
+        }
+        self.ctx.input.gamepads.update(gamepads);
+        self.ctx.pointer_lock.update(pointer_locked, fullscreen);
+    }
+
+    fn handle_ui_messages(&mut self) {
+        // It is very important to "pump" messages from UI. Even if don't need to
+        // respond to such message, you should call this method, otherwise UI
+        // might behave very weird.
+        while let Some(ui_message) = self.ctx.engine.user_interface.poll_message() {
+            self.states.handle_ui_message(&mut self.ctx, &ui_message);
+        }
+    }
+
+    fn update(&mut self, _tick: u64) {
+        self.states.update(&mut self.ctx);
+        self.scene_manager.update(&mut self.ctx);
+
+        if let Some(game_scene) = self.ctx.scene.as_mut() {
+            let dt = self.ctx.clock.timestep();
+            game_scene.update(&mut self.ctx.engine, &self.ctx.input, dt);
+        }
+
+        if self.ctx.input.was_action_pressed(Action::SaveRecording) {
+            self.save_recording();
+        }
+    }
+
+    fn end_step(&mut self) {
+        self.ctx.input.end_step();
+        self.ctx.pointer_lock.end_step();
+
+        if let Some(hot_reload) = self.hot_reload.as_mut() {
+            hot_reload.update(&mut self.ctx);
+        }
+
+        // Update engine each step.
+        let dt = self.ctx.clock.timestep();
+        self.ctx.engine.update(dt);
+    }
+}
+
+/// Runs the main loop, never returns.
+pub fn run(event_loop: EventLoop<()>, mut app: App) -> ! {
+    // Run the event loop of the main window. which will respond to OS and window events and update
+    // engine's state accordingly. Engine lets you to decide which event should be handled,
+    // this is minimal working example if how it should be.
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::MainEventsCleared => {
+                // This main game loop - it has fixed time step which means that game
+                // code will run at fixed speed even if renderer can't give you desired
+                // 60 fps.
+                if app.replay.is_none() {
+                    for touch in app.ctx.platform.touch_events() {
+                        app.handle_touch(touch);
+                    }
+                }
+
+                let first_tick = app.ctx.clock.ticks();
+                let steps = app.ctx.clock.begin_frame();
+                run_steps(&mut app, first_tick, steps);
+                if app.exit_requested {
+                    *control_flow = ControlFlow::Exit
+                }
+
+                // Rendering must be explicitly requested and handled after RedrawRequested event is received.
+                app.ctx.engine.get_window().request_redraw();
+            }
+            Event::RedrawRequested(_) => {
+                // Render at max speed - it is not tied to the game code. The camera is drawn
+                // between its last two steps, so it moves smoothly at any frame rate.
+                if let Some(game_scene) = app.ctx.scene.as_ref() {
+                    game_scene.interpolate_camera(&mut app.ctx.engine, app.ctx.clock.alpha());
+                }
+                let frame_time = app.ctx.clock.frame_time();
+                app.ctx.engine.render(frame_time).unwrap();
+            }
+            Event::WindowEvent { ref event, .. } => {
+                match event {
+                    WindowEvent::CloseRequested => {
+                        *control_flow = ControlFlow::Exit
+                    },
+                    WindowEvent::Resized(size) => {
+                        app.on_resize(size.width, size.height);
+                    },
+                    WindowEvent::Focused(focused) => {
+                        app.ctx.platform.set_focused(*focused);
+                        // Game time stands still in the background. Replays keep going, regression
+                        // runs are usually left alone in a window that never gets focus.
+                        if app.replay.is_none() {
+                            if *focused {
+                                app.ctx.clock.resume();
+                            } else {
+                                app.ctx.clock.pause();
+                            }
+                        }
+                        // Alt-tab on the desktop, browsers let go of the pointer by themselves.
+                        if !*focused {
+                            app.ctx
+                                .pointer_lock
+                                .exit_lock(&*app.ctx.platform, app.ctx.engine.get_window());
+                        }
+                    },
+                    _ => (),
+                }
+            }
+            Event::LoopDestroyed => app.save_recording(),
+            _ => *control_flow = ControlFlow::Poll,
+        }
+
+        match RecordedEvent::from_event(&event) {
+            // Live input is ignored during a replay.
+            Some(_) if app.replay.is_some() => (),
+            Some(recorded) => {
+                if let Some(recorder) = app.recorder.as_mut() {
+                    recorder.record(app.ctx.clock.ticks(), recorded);
+                }
+                app.dispatch_event(&event);
+            }
+            None => app.dispatch_event(&event),
+        }
+    })
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// A resource that failed to load.
+#[derive(Clone, Debug)]
+pub enum AssetError {
+    Texture { path: String, reason: String },
+    Model { path: String, reason: String },
+    /// Plain file, e.g. the asset manifest.
+    File { path: String, reason: String },
+    /// The file loaded, but its contents are wrong. One message per problem.
+    Invalid { path: String, errors: Vec<String> },
+    /// The id isn't listed in the asset manifest.
+    UnknownId { id: String },
+}
+
+impl AssetError {
+    pub fn path(&self) -> &str {
+        match self {
+            AssetError::Texture { path, .. }
+            | AssetError::Model { path, .. }
+            | AssetError::File { path, .. }
+            | AssetError::Invalid { path, .. } => path,
+            AssetError::UnknownId { id } => id,
+        }
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Texture { path, reason } => {
+                write!(f, "failed to load texture {}: {}", path, reason)
+            }
+            AssetError::Model { path, reason } => {
+                write!(f, "failed to load model {}: {}", path, reason)
+            }
+            AssetError::File { path, reason } => {
+                write!(f, "failed to load file {}: {}", path, reason)
+            }
+            AssetError::Invalid { path, errors } => {
+                write!(f, "invalid {}:", path)?;
+                for error in errors {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+            AssetError::UnknownId { id } => write!(f, "no asset {} in the manifest", id),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
@@ -0,0 +1,70 @@
+// Stand-ins for assets that failed to load. They are loud on purpose, a magenta checkerboard is
+// hard to miss in a level.
+
+use rg3d::{
+    core::{algebra::Matrix4, pool::Handle},
+    resource::texture::{Texture, TextureKind, TexturePixelKind},
+    scene::{
+        base::BaseBuilder,
+        camera::SkyBox,
+        graph::Graph,
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData},
+            MeshBuilder,
+        },
+        node::Node,
+        transform::Transform,
+    },
+};
+
+use std::sync::{Arc, RwLock};
+
+const CHECKER_SIZE: u32 = 8;
+
+pub fn placeholder_texture() -> Texture {
+    let mut pixels = Vec::with_capacity((CHECKER_SIZE * CHECKER_SIZE * 4) as usize);
+    for y in 0..CHECKER_SIZE {
+        for x in 0..CHECKER_SIZE {
+            let pixel = if (x + y) % 2 == 0 {
+                [255, 0, 255, 255]
+            } else {
+                [0, 0, 0, 255]
+            };
+            pixels.extend_from_slice(&pixel);
+        }
+    }
+
+    Texture::from_bytes(
+        TextureKind::Rectangle {
+            width: CHECKER_SIZE,
+            height: CHECKER_SIZE,
+        },
+        TexturePixelKind::RGBA8,
+        pixels,
+    )
+    .unwrap()
+}
+
+/// The placeholder texture on every face, in place of a skybox that failed to load.
+pub fn placeholder_skybox() -> SkyBox {
+    let texture = placeholder_texture();
+    SkyBox {
+        front: Some(texture.clone()),
+        back: Some(texture.clone()),
+        left: Some(texture.clone()),
+        right: Some(texture.clone()),
+        top: Some(texture.clone()),
+        bottom: Some(texture),
+    }
+}
+
+/// A unit cube with the placeholder texture, in place of a model that failed to load.
+pub fn placeholder_mesh(graph: &mut Graph, transform: Transform) -> Handle<Node> {
+    MeshBuilder::new(BaseBuilder::new().with_local_transform(transform))
+        .with_surfaces(vec![SurfaceBuilder::new(Arc::new(RwLock::new(
+            SurfaceData::make_cube(Matrix4::identity()),
+        )))
+        .with_diffuse_texture(placeholder_texture())
+        .build()])
+        .build(graph)
+}
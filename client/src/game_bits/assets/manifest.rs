@@ -0,0 +1,321 @@
+// The asset manifest (assets/manifest.json) lists every asset under a logical id together with a
+// hash of its contents. Code asks for assets by id, so moving a file only touches the manifest,
+// and in the browser the hash goes into the url so a deploy doesn't leave stale textures in the
+// cache. Refresh the hashes with `cargo run --bin manifest` after changing an asset.
+
+use rg3d::{core::futures::future::join_all, engine::resource_manager::ResourceManager};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+use super::{AssetError, AssetTracker};
+use crate::game_bits::skybox::SkyboxDefinition;
+
+pub const MANIFEST_PATH: &str = "assets/manifest.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Texture,
+    Model,
+    /// Any other file, e.g. a level description.
+    Data,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub kind: AssetKind,
+    /// Relative to the asset root, the same path the resource manager gets.
+    pub path: String,
+    #[serde(default)]
+    pub hash: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    pub assets: BTreeMap<String, ManifestEntry>,
+    /// Named lists of asset ids that get preloaded together.
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub skyboxes: BTreeMap<String, SkyboxDefinition>,
+    // Only the browser caches assets, and natively a query string isn't part of a valid file
+    // name, so the front end decides.
+    #[serde(skip)]
+    hashed_urls: bool,
+}
+
+impl Manifest {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let manifest: Manifest = serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+        for (group, ids) in manifest.groups.iter() {
+            if let Some(id) = ids.iter().find(|id| !manifest.assets.contains_key(*id)) {
+                return Err(format!("group {} lists unknown asset {}", group, id));
+            }
+        }
+
+        for (name, skybox) in manifest.skyboxes.iter() {
+            for id in skybox.faces().iter() {
+                match manifest.assets.get(*id) {
+                    Some(entry) if entry.kind == AssetKind::Texture => (),
+                    _ => return Err(format!("skybox {} needs texture {}", name, id)),
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Fetches and parses the manifest at `url`.
+    pub async fn load(
+        tracker: &AssetTracker,
+        url: &str,
+        hashed_urls: bool,
+    ) -> Result<Self, AssetError> {
+        let file_error = |reason: String| AssetError::File {
+            path: url.to_string(),
+            reason,
+        };
+
+        let data = tracker.file(url).await?;
+        let json = String::from_utf8(data).map_err(|err| file_error(err.to_string()))?;
+        let mut manifest = Self::from_json(&json).map_err(file_error)?;
+        manifest.hashed_urls = hashed_urls;
+
+        Ok(manifest)
+    }
+
+    pub fn entry(&self, id: &str) -> Result<&ManifestEntry, AssetError> {
+        self.assets
+            .get(id)
+            .ok_or_else(|| AssetError::UnknownId { id: id.to_string() })
+    }
+
+    /// Url to request the asset with, for the resource manager.
+    pub fn url(&self, id: &str) -> Result<String, AssetError> {
+        self.entry(id).map(|entry| self.entry_url(entry))
+    }
+
+    /// Like `url`, but also takes a plain path, e.g. a level picked on the command line. Paths
+    /// that aren't in the manifest are returned as they are.
+    pub fn resolve(&self, id_or_path: &str) -> String {
+        self.assets
+            .get(id_or_path)
+            .or_else(|| self.assets.values().find(|entry| entry.path == id_or_path))
+            .map_or_else(|| id_or_path.to_string(), |entry| self.entry_url(entry))
+    }
+
+    fn entry_url(&self, entry: &ManifestEntry) -> String {
+        // Model loaders pick the file format by extension, so models can't have a query string.
+        if self.hashed_urls && entry.kind != AssetKind::Model && !entry.hash.is_empty() {
+            format!("{}?v={}", entry.path, entry.hash)
+        } else {
+            entry.path.clone()
+        }
+    }
+}
+
+/// 64-bit FNV-1a of the file contents. Only used to tell versions apart, it doesn't need to be
+/// cryptographic.
+pub fn content_hash(data: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Requests every asset of `groups` at once and waits until all of them are done. The resource
+/// manager keeps them around for a while, so whatever requests them next gets them right away.
+/// Failed assets and unknown groups are returned and also end up on the tracker's list.
+pub async fn preload(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    groups: &[&str],
+) -> Vec<AssetError> {
+    let mut errors = Vec::new();
+    let mut requests = Vec::new();
+
+    for group in groups {
+        let ids = match manifest.groups.get(*group) {
+            Some(ids) => ids,
+            None => {
+                errors.push(tracker.record_failure(AssetError::UnknownId {
+                    id: group.to_string(),
+                }));
+                continue;
+            }
+        };
+
+        for id in ids {
+            // Groups are checked against the asset list when the manifest is parsed.
+            let entry = &manifest.assets[id];
+            let url = manifest.entry_url(entry);
+            requests.push(async move {
+                match entry.kind {
+                    AssetKind::Texture => tracker.texture(resource_manager, &url).await.map(|_| ()),
+                    AssetKind::Model => tracker.model(resource_manager, &url).await.map(|_| ()),
+                    // Nothing keeps plain files around, this only warms up the http cache.
+                    AssetKind::Data => tracker.file(&url).await.map(|_| ()),
+                }
+            });
+        }
+    }
+
+    for result in join_all(requests).await {
+        if let Err(err) = result {
+            errors.push(err);
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "assets": {
+            "textures.crate": { "kind": "texture", "path": "assets/textures/crate.jpg", "hash": "0123456789abcdef" },
+            "textures.sky": { "kind": "texture", "path": "assets/textures/sky.jpg" },
+            "models.barrel": { "kind": "model", "path": "assets/models/barrel.FBX", "hash": "fedcba9876543210" },
+            "levels.test": { "kind": "data", "path": "assets/levels/test.json", "hash": "00000000000000ff" }
+        },
+        "groups": { "props": ["textures.crate", "models.barrel"] },
+        "skyboxes": {
+            "plain": {
+                "front": "textures.sky", "back": "textures.sky", "left": "textures.sky",
+                "right": "textures.sky", "top": "textures.sky", "bottom": "textures.sky"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn parses_assets_groups_and_skyboxes() {
+        let manifest = Manifest::from_json(MANIFEST).unwrap();
+        assert_eq!(manifest.assets.len(), 4);
+        assert_eq!(
+            manifest.entry("models.barrel").unwrap().kind,
+            AssetKind::Model
+        );
+        assert_eq!(manifest.entry("textures.sky").unwrap().hash, "");
+        assert_eq!(
+            manifest.groups["props"],
+            vec!["textures.crate", "models.barrel"]
+        );
+        assert!(manifest.skyboxes.contains_key("plain"));
+        assert!(matches!(
+            manifest.entry("textures.missing"),
+            Err(AssetError::UnknownId { .. })
+        ));
+    }
+
+    #[test]
+    fn groups_and_skyboxes_are_optional() {
+        let manifest = Manifest::from_json(r#"{ "assets": {} }"#).unwrap();
+        assert!(manifest.groups.is_empty());
+        assert!(manifest.skyboxes.is_empty());
+    }
+
+    #[test]
+    fn bad_manifests_are_rejected() {
+        assert!(Manifest::from_json("").is_err());
+        assert!(Manifest::from_json(r#"{ "groups": {} }"#).is_err());
+        assert!(Manifest::from_json(
+            r#"{ "assets": { "a": { "kind": "sound", "path": "a.ogg" } } }"#
+        )
+        .is_err());
+
+        let err =
+            Manifest::from_json(r#"{ "assets": {}, "groups": { "props": ["models.barrel"] } }"#)
+                .unwrap_err();
+        assert_eq!(err, "group props lists unknown asset models.barrel");
+    }
+
+    #[test]
+    fn skybox_faces_must_be_textures() {
+        let json = MANIFEST.replace(r#""top": "textures.sky""#, r#""top": "models.barrel""#);
+        assert_eq!(
+            Manifest::from_json(&json).unwrap_err(),
+            "skybox plain needs texture models.barrel"
+        );
+
+        let json = MANIFEST.replace(r#""top": "textures.sky""#, r#""top": "textures.nope""#);
+        assert!(Manifest::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn hashes_go_into_urls_only_when_asked() {
+        let mut manifest = Manifest::from_json(MANIFEST).unwrap();
+        assert_eq!(
+            manifest.url("textures.crate").unwrap(),
+            "assets/textures/crate.jpg"
+        );
+
+        manifest.hashed_urls = true;
+        assert_eq!(
+            manifest.url("textures.crate").unwrap(),
+            "assets/textures/crate.jpg?v=0123456789abcdef"
+        );
+        assert_eq!(
+            manifest.url("levels.test").unwrap(),
+            "assets/levels/test.json?v=00000000000000ff"
+        );
+        // Models keep their extension, assets without a hash stay as they are.
+        assert_eq!(
+            manifest.url("models.barrel").unwrap(),
+            "assets/models/barrel.FBX"
+        );
+        assert_eq!(
+            manifest.url("textures.sky").unwrap(),
+            "assets/textures/sky.jpg"
+        );
+    }
+
+    #[test]
+    fn resolve_takes_ids_and_paths() {
+        let mut manifest = Manifest::from_json(MANIFEST).unwrap();
+        manifest.hashed_urls = true;
+        let hashed = "assets/textures/crate.jpg?v=0123456789abcdef";
+        assert_eq!(manifest.resolve("textures.crate"), hashed);
+        assert_eq!(manifest.resolve("assets/textures/crate.jpg"), hashed);
+        assert_eq!(
+            manifest.resolve("assets/elsewhere.rgs"),
+            "assets/elsewhere.rgs"
+        );
+    }
+
+    #[test]
+    fn content_hash_is_fnv1a_64() {
+        // Test vectors from the FNV reference.
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+        assert_eq!(content_hash(b"foobar"), "85944171f73967e8");
+    }
+
+    #[test]
+    fn shipped_manifest_is_valid_and_up_to_date() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let json = std::fs::read_to_string(root.join(MANIFEST_PATH)).unwrap();
+        let manifest = Manifest::from_json(&json).unwrap();
+
+        for (id, entry) in manifest.assets.iter() {
+            let data = std::fs::read(root.join(&entry.path)).unwrap();
+            assert_eq!(
+                content_hash(&data),
+                entry.hash,
+                "{} is out of date, run `cargo run --bin manifest`",
+                id
+            );
+        }
+    }
+}
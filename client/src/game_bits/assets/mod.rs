@@ -0,0 +1,11 @@
+mod error;
+mod fallback;
+mod manifest;
+mod tracker;
+
+pub use self::{
+    error::AssetError,
+    fallback::{placeholder_mesh, placeholder_skybox, placeholder_texture},
+    manifest::{content_hash, preload, AssetKind, Manifest, ManifestEntry, MANIFEST_PATH},
+    tracker::{AssetProgress, AssetTracker},
+};
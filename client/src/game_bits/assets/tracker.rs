@@ -0,0 +1,157 @@
+use rg3d::{
+    core::io,
+    engine::resource_manager::ResourceManager,
+    resource::{model::Model, texture::Texture},
+};
+
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use super::AssetError;
+
+/// Snapshot of how far asset loading got.
+#[derive(Clone, Debug, Default)]
+pub struct AssetProgress {
+    pub requested: usize,
+    pub completed: usize,
+    pub failed: Vec<AssetError>,
+    /// Path of the asset that finished last.
+    pub last_finished: Option<String>,
+}
+
+impl AssetProgress {
+    /// Fraction of requested assets that are done, failed ones included, in 0..1.
+    pub fn fraction(&self) -> f32 {
+        if self.requested == 0 {
+            0.0
+        } else {
+            (self.completed + self.failed.len()) as f32 / self.requested as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.completed + self.failed.len() == self.requested
+    }
+}
+
+#[derive(Default)]
+struct TrackerState {
+    progress: AssetProgress,
+    // Every path counts once, so preloading an asset and requesting it again later doesn't show
+    // up twice.
+    paths: HashSet<String>,
+}
+
+/// Counts requested, completed and failed resources. Clones share the same counters, so the
+/// loading future can update it while the loading screen reads it.
+#[derive(Clone, Default)]
+pub struct AssetTracker {
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl AssetTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn texture(
+        &self,
+        resource_manager: &ResourceManager,
+        path: &str,
+    ) -> Result<Texture, AssetError> {
+        let request = resource_manager.request_texture(path);
+        self.track(
+            path,
+            async { request.await.map_err(resource_error) },
+            |path, reason| AssetError::Texture { path, reason },
+        )
+        .await
+    }
+
+    pub async fn model(
+        &self,
+        resource_manager: &ResourceManager,
+        path: &str,
+    ) -> Result<Model, AssetError> {
+        let request = resource_manager.request_model(path);
+        self.track(
+            path,
+            async { request.await.map_err(resource_error) },
+            |path, reason| AssetError::Model { path, reason },
+        )
+        .await
+    }
+
+    /// Raw file contents, fetched over http in the browser.
+    pub async fn file(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        self.track(
+            path,
+            async {
+                io::load_file(path)
+                    .await
+                    .map_err(|err| format!("{:?}", err))
+            },
+            |path, reason| AssetError::File { path, reason },
+        )
+        .await
+    }
+
+    async fn track<F, T>(
+        &self,
+        path: &str,
+        request: F,
+        make_error: fn(String, String) -> AssetError,
+    ) -> Result<T, AssetError>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        let first_request = {
+            let mut state = self.state.lock().unwrap();
+            let first_request = state.paths.insert(path.to_string());
+            if first_request {
+                state.progress.requested += 1;
+            }
+            first_request
+        };
+
+        let result = request
+            .await
+            .map_err(|reason| make_error(path.to_string(), reason));
+
+        if first_request {
+            let progress = &mut self.state.lock().unwrap().progress;
+            match &result {
+                Ok(_) => progress.completed += 1,
+                Err(err) => progress.failed.push(err.clone()),
+            }
+            progress.last_finished = Some(path.to_string());
+        }
+
+        result
+    }
+
+    /// Counts an error that happened before anything could be requested, e.g. an unknown
+    /// manifest id, and hands it back.
+    pub fn record_failure(&self, error: AssetError) -> AssetError {
+        let progress = &mut self.state.lock().unwrap().progress;
+        progress.requested += 1;
+        progress.failed.push(error.clone());
+        error
+    }
+
+    pub fn progress(&self) -> AssetProgress {
+        self.state.lock().unwrap().progress.clone()
+    }
+}
+
+// Resource futures resolve to `Err(None)` when the loader gave no reason.
+fn resource_error<E: Debug>(err: Option<Arc<E>>) -> String {
+    match err {
+        Some(err) => format!("{:?}", err),
+        None => "unknown error".to_string(),
+    }
+}
@@ -0,0 +1,77 @@
+// Mouse look for the player's own eyes. Yaw turns the whole player, pitch only tilts the camera,
+// and pitch stops short of straight up and down so the view never flips over.
+
+use rg3d::core::algebra::{UnitQuaternion, Vector3};
+
+// Degrees, looking further up or down than this turns the view upside down.
+const MAX_PITCH: f32 = 89.0;
+
+/// How mouse motion turns into looking around.
+#[derive(Clone, Copy, Debug)]
+pub struct LookSettings {
+    /// Degrees per pixel of mouse motion.
+    pub sensitivity: f32,
+    /// Moving the mouse up looks down, pushing a stick up too.
+    pub invert_y: bool,
+    /// Degrees per second with a stick pushed all the way.
+    pub stick_speed: f32,
+}
+
+impl Default for LookSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.15,
+            invert_y: false,
+            stick_speed: 180.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstPersonCamera {
+    // Both in degrees. Yaw 0 looks down +Z like spawn points do, positive pitch looks down.
+    yaw: f32,
+    pitch: f32,
+}
+
+impl FirstPersonCamera {
+    pub fn new(yaw: f32) -> Self {
+        Self { yaw, pitch: 0.0 }
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Turns by a mouse motion delta in pixels.
+    pub fn process_mouse_motion(&mut self, dx: f64, dy: f64, settings: &LookSettings) {
+        let dy = if settings.invert_y { -dy } else { dy };
+
+        // Keep yaw small, it only ever goes into sines and cosines.
+        self.yaw = (self.yaw - dx as f32 * settings.sensitivity) % 360.0;
+        self.pitch = (self.pitch + dy as f32 * settings.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Turns by a stick position, -1 to 1 on both axes with down being positive, held for `dt`
+    /// seconds.
+    pub fn process_stick(&mut self, x: f32, y: f32, dt: f32, settings: &LookSettings) {
+        // Same as moving the mouse by this many pixels.
+        let scale = settings.stick_speed * dt / settings.sensitivity;
+        self.process_mouse_motion((x * scale) as f64, (y * scale) as f64, settings);
+    }
+
+    /// Rotation of the player's body, just the yaw.
+    pub fn body_rotation(&self) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.yaw.to_radians())
+    }
+
+    /// Rotation of the camera node.
+    pub fn rotation(&self) -> UnitQuaternion<f32> {
+        self.body_rotation()
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.pitch.to_radians())
+    }
+}
@@ -0,0 +1,60 @@
+// Noclip camera for looking at the level from anywhere. It flies where it looks, through walls,
+// and leaves the player standing where they were.
+
+use rg3d::core::algebra::{UnitQuaternion, Vector3};
+
+use super::FirstPersonCamera;
+use crate::game_bits::input::{Action, Input};
+
+// Meters per second, times FAST_FACTOR while the fast key is held.
+const FLY_SPEED: f32 = 8.0;
+const FAST_FACTOR: f32 = 4.0;
+
+pub struct FreeFlyCamera {
+    /// Feed it mouse motion like the player's own.
+    pub look: FirstPersonCamera,
+    position: Vector3<f32>,
+}
+
+impl FreeFlyCamera {
+    /// Starts at `position` looking the way `look` does, usually where the camera already is.
+    pub fn new(position: Vector3<f32>, look: FirstPersonCamera) -> Self {
+        Self { look, position }
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn rotation(&self) -> UnitQuaternion<f32> {
+        self.look.rotation()
+    }
+
+    /// Flies with the movement keys, called once per time step.
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        let rotation = self.look.rotation();
+        // Unlike walking, forward includes the pitch.
+        let forward = rotation * Vector3::z();
+        // Looking down +Z, +X is on the left.
+        let left = rotation * Vector3::x();
+
+        let (right_amount, forward_amount) = input.move_vector();
+        let mut direction = forward * forward_amount - left * right_amount;
+        if input.is_action_down(Action::FlyUp) {
+            direction += Vector3::y();
+        }
+        if input.is_action_down(Action::FlyDown) {
+            direction -= Vector3::y();
+        }
+        if direction.norm() > 1.0 {
+            direction = direction.normalize();
+        }
+
+        let speed = if input.is_action_down(Action::FlyFast) {
+            FLY_SPEED * FAST_FACTOR
+        } else {
+            FLY_SPEED
+        };
+        self.position += direction * speed * dt;
+    }
+}
@@ -0,0 +1,29 @@
+// Camera controllers, they turn player input into where the level camera is and where it looks.
+
+mod first_person;
+mod free_fly;
+mod third_person;
+
+pub use self::{
+    first_person::{FirstPersonCamera, LookSettings},
+    free_fly::FreeFlyCamera,
+    third_person::ThirdPersonCamera,
+};
+
+/// Which controller moves the level camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Out of the local player's eyes.
+    FirstPerson,
+    /// Orbiting the local player.
+    ThirdPerson,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::FirstPerson => CameraMode::ThirdPerson,
+            CameraMode::ThirdPerson => CameraMode::FirstPerson,
+        }
+    }
+}
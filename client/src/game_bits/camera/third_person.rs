@@ -0,0 +1,115 @@
+// Over the shoulder view, the camera orbits the player at a distance. It turns with the same yaw
+// and pitch as the first-person view, so switching between the two keeps the player facing the
+// same way. The point it looks at trails the player a little so movement feels smooth, and the
+// camera moves in front of walls instead of looking through them.
+
+use rg3d::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        math::ray::Ray,
+    },
+    physics::geometry::InteractionGroups,
+    scene::{
+        physics::{Intersection, RayCastOptions},
+        Scene,
+    },
+};
+
+// Meters from the player, the wheel zooms in between.
+const MIN_DISTANCE: f32 = 1.5;
+const MAX_DISTANCE: f32 = 10.0;
+const DEFAULT_DISTANCE: f32 = 4.0;
+// Meters per wheel step.
+const ZOOM_STEP: f32 = 0.5;
+// How quickly the camera catches up, higher is stiffer. Roughly the fraction of the way that is
+// left after a second is e^-rate.
+const FOLLOW_RATE: f32 = 10.0;
+const ZOOM_RATE: f32 = 8.0;
+// Kept between the camera and a wall, so the near plane doesn't cut into it.
+const WALL_MARGIN: f32 = 0.2;
+
+pub struct ThirdPersonCamera {
+    /// Meters from the target, as zoomed with the wheel.
+    distance: f32,
+    // Distance after moving in for walls, eases back out towards `distance`.
+    current_distance: f32,
+    // The point the camera orbits, trailing behind the target. None until the first update.
+    focus: Option<Vector3<f32>>,
+    // Kept around so casting rays doesn't allocate each step.
+    intersections: Vec<Intersection>,
+}
+
+impl Default for ThirdPersonCamera {
+    fn default() -> Self {
+        Self {
+            distance: DEFAULT_DISTANCE,
+            current_distance: DEFAULT_DISTANCE,
+            focus: None,
+            intersections: Vec::new(),
+        }
+    }
+}
+
+// Fraction of the way to move in a step of `dt` seconds, framerate independent.
+fn ease(rate: f32, dt: f32) -> f32 {
+    1.0 - (-rate * dt).exp()
+}
+
+impl ThirdPersonCamera {
+    /// Moves closer for positive steps, further away for negative ones.
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance - steps * ZOOM_STEP).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    /// Jumps straight to the target on the next update instead of gliding over, e.g. after a
+    /// respawn.
+    pub fn snap(&mut self) {
+        self.focus = None;
+    }
+
+    // How far the camera can go back from `focus` before something is in the way.
+    fn free_distance(&mut self, scene: &Scene, focus: Vector3<f32>, back: Vector3<f32>) -> f32 {
+        scene.physics.cast_ray(
+            RayCastOptions {
+                ray: Ray::new(focus, back),
+                max_len: self.distance,
+                groups: InteractionGroups::all(),
+                sort_results: true,
+            },
+            &mut self.intersections,
+        );
+
+        match self.intersections.first() {
+            Some(hit) => (hit.toi - WALL_MARGIN).max(0.0),
+            None => self.distance,
+        }
+    }
+
+    /// Position of the camera orbiting `target` and looking along `rotation`. Call once per
+    /// time step.
+    pub fn update(
+        &mut self,
+        scene: &Scene,
+        target: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        dt: f32,
+    ) -> Vector3<f32> {
+        let focus = match self.focus {
+            Some(focus) => focus.lerp(&target, ease(FOLLOW_RATE, dt)),
+            None => {
+                self.current_distance = self.distance;
+                target
+            }
+        };
+        self.focus = Some(focus);
+
+        // The camera looks down its +Z, so it sits on the -Z side of the focus.
+        let back = rotation * -Vector3::z();
+        let free_distance = self.free_distance(scene, focus, back);
+        self.current_distance += (self.distance - self.current_distance) * ease(ZOOM_RATE, dt);
+        // Walls pull the camera in right away, it only eases back out.
+        self.current_distance = self.current_distance.min(free_distance);
+
+        focus + back * self.current_distance
+    }
+}
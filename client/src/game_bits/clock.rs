@@ -0,0 +1,222 @@
+use rg3d::core::instant::Instant;
+
+// At most this many steps are run per frame. If the game can't keep up the extra time is dropped,
+// otherwise every slow frame would queue even more work for the next one.
+const MAX_STEPS: u32 = 8;
+
+/// Fixed-timestep game clock.
+///
+/// Real frame time is added to an accumulator and consumed in `timestep` sized steps, whatever is
+/// left over carries into the next frame. Rendering happens somewhere between two steps, `alpha`
+/// says how far, so it can interpolate between the previous and the current game state.
+///
+/// A paused clock runs no steps at all, so nothing that runs on steps moves: the game, the states
+/// and the scene manager. Menus that should keep working while the game stands still use
+/// `GameScene::paused` instead.
+pub struct GameClock {
+    timestep: f32,
+    time_scale: f32,
+    paused: bool,
+    accumulator: f32,
+    last_frame: Option<Instant>,
+    frame_time: f32,
+    ticks: u64,
+}
+
+impl GameClock {
+    pub fn new(timestep: f32) -> Self {
+        Self {
+            timestep,
+            time_scale: 1.0,
+            paused: false,
+            accumulator: 0.0,
+            last_frame: None,
+            frame_time: 0.0,
+            ticks: 0,
+        }
+    }
+
+    /// Measures the time since the previous call and returns how many steps to run this frame.
+    pub fn begin_frame(&mut self) -> u32 {
+        let now = Instant::now();
+        let frame_time = match self.last_frame {
+            Some(last_frame) => (now - last_frame).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last_frame = Some(now);
+        self.advance(frame_time)
+    }
+
+    /// Adds `frame_time` seconds of real time and returns how many steps to run.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.frame_time = frame_time;
+        if self.paused {
+            return 0;
+        }
+        self.accumulator += frame_time * self.time_scale;
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep && steps < MAX_STEPS {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        // Spiral of death guard, keep only the fraction of a step that alpha needs.
+        if self.accumulator >= self.timestep {
+            self.accumulator %= self.timestep;
+        }
+
+        self.ticks += steps as u64;
+        steps
+    }
+
+    /// Length of one game step in seconds, this is the dt for game logic.
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    /// Fraction of a step (0..1) between the last game step and now.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.timestep
+    }
+
+    /// Real time the last frame took, unaffected by pause and time scale.
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+
+    /// Number of game steps run so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Stops game time until `resume`. The accumulator is kept, so `alpha` and with it the
+    /// rendered frame stay where they were.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Starts game time again from now on. The time spent paused is never caught up on, not even
+    /// if `begin_frame` wasn't called in the meantime.
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.last_frame = None;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Speeds up (> 1) or slows down (< 1) game time relative to real time, e.g. to run through
+    /// a replay faster. Steps stay the same length, there are just more or fewer of them.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 0.25;
+
+    #[test]
+    fn runs_whole_steps() {
+        let mut clock = GameClock::new(STEP);
+        assert_eq!(clock.advance(0.1), 0);
+        assert_eq!(clock.advance(0.1), 0);
+        // 0.3 so far.
+        assert_eq!(clock.advance(0.1), 1);
+        assert_eq!(clock.advance(0.5), 2);
+        assert_eq!(clock.ticks(), 3);
+    }
+
+    #[test]
+    fn alpha_is_the_leftover() {
+        let mut clock = GameClock::new(STEP);
+        clock.advance(0.375);
+        assert!((clock.alpha() - 0.5).abs() < 1e-5);
+        clock.advance(0.125);
+        assert!(clock.alpha().abs() < 1e-5);
+    }
+
+    #[test]
+    fn caps_steps_per_frame() {
+        let mut clock = GameClock::new(STEP);
+        assert_eq!(clock.advance(STEP * 100.0), MAX_STEPS);
+        assert_eq!(clock.ticks(), MAX_STEPS as u64);
+    }
+
+    #[test]
+    fn drops_time_it_cant_catch_up_on() {
+        let mut clock = GameClock::new(STEP);
+        clock.advance(STEP * 100.5);
+        // Only the fraction is kept, the next frame doesn't inherit 92 steps of backlog.
+        assert!(clock.alpha() < 1.0);
+        assert!((clock.alpha() - 0.5).abs() < 1e-3);
+        assert_eq!(clock.advance(0.0), 0);
+    }
+
+    #[test]
+    fn time_scale_changes_the_step_count() {
+        let mut clock = GameClock::new(STEP);
+        clock.set_time_scale(2.0);
+        assert_eq!(clock.advance(STEP), 2);
+        // Real time is still reported as it was.
+        assert_eq!(clock.frame_time(), STEP);
+
+        clock.set_time_scale(0.0);
+        assert_eq!(clock.advance(10.0), 0);
+
+        // Negative scales would run time backwards.
+        clock.set_time_scale(-1.0);
+        assert_eq!(clock.advance(10.0), 0);
+    }
+
+    #[test]
+    fn paused_clock_runs_no_steps() {
+        let mut clock = GameClock::new(STEP);
+        clock.advance(0.375);
+        clock.pause();
+        assert!(clock.is_paused());
+
+        assert_eq!(clock.advance(10.0), 0);
+        assert_eq!(clock.ticks(), 1);
+        // Rendering stays where it was.
+        assert!((clock.alpha() - 0.5).abs() < 1e-5);
+        assert_eq!(clock.frame_time(), 10.0);
+    }
+
+    #[test]
+    fn resume_carries_on_without_catching_up() {
+        let mut clock = GameClock::new(STEP);
+        clock.advance(0.375);
+        clock.pause();
+        clock.advance(10.0);
+        clock.resume();
+        assert!(!clock.is_paused());
+
+        // The half step from before the pause is still there.
+        assert_eq!(clock.advance(0.125), 1);
+        assert!(clock.alpha().abs() < 1e-5);
+        assert_eq!(clock.ticks(), 2);
+    }
+
+    #[test]
+    fn resume_restarts_the_frame_timer() {
+        let mut clock = GameClock::new(STEP);
+        clock.begin_frame();
+        clock.pause();
+        clock.resume();
+        // However long ago the last frame was, the first one after resuming takes no time.
+        assert_eq!(clock.begin_frame(), 0);
+        assert_eq!(clock.frame_time(), 0.0);
+
+        // Resuming a running clock changes nothing.
+        clock.advance(0.375);
+        clock.resume();
+        assert!((clock.alpha() - 0.5).abs() < 1e-5);
+    }
+}
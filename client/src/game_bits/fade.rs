@@ -0,0 +1,118 @@
+// Fades the screen to black and back, to hide things like a skybox swap or a level change. The
+// overlay only exists while the screen isn't fully clear, so it doesn't eat clicks otherwise.
+
+use rg3d::{
+    core::{color::Color, pool::Handle},
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        message::{MessageDirection, WidgetMessage},
+        widget::WidgetBuilder,
+    },
+};
+
+use super::app::{GameContext, UiNode};
+
+pub struct ScreenFade {
+    overlay: Handle<UiNode>,
+    // 0 is clear, 1 is black.
+    opacity: f32,
+    target: f32,
+    // Opacity change per second.
+    speed: f32,
+}
+
+impl ScreenFade {
+    pub fn new() -> Self {
+        Self {
+            overlay: Handle::NONE,
+            opacity: 0.0,
+            target: 0.0,
+            speed: 0.0,
+        }
+    }
+
+    /// Starts fading to black, a zero duration goes black on the next update.
+    pub fn fade_out(&mut self, duration: f32) {
+        self.start(1.0, duration);
+    }
+
+    /// Starts fading back from black.
+    pub fn fade_in(&mut self, duration: f32) {
+        self.start(0.0, duration);
+    }
+
+    fn start(&mut self, target: f32, duration: f32) {
+        self.target = target;
+        self.speed = if duration > 0.0 {
+            1.0 / duration
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.opacity >= 1.0
+    }
+
+    /// True when the last fade reached its target.
+    pub fn is_finished(&self) -> bool {
+        (self.opacity - self.target).abs() < f32::EPSILON
+    }
+
+    pub fn update(&mut self, ctx: &mut GameContext, dt: f32) {
+        if self.is_finished() {
+            return;
+        }
+
+        let step = self.speed * dt;
+        self.opacity = if self.target > self.opacity {
+            (self.opacity + step).min(self.target)
+        } else {
+            (self.opacity - step).max(self.target)
+        };
+
+        if self.opacity <= 0.0 {
+            self.clear(ctx);
+            return;
+        }
+
+        if self.overlay.is_none() {
+            // The root of the UI doesn't stretch its children, so size the overlay by hand.
+            self.overlay = BorderBuilder::new(
+                WidgetBuilder::new()
+                    .with_width(ctx.screen_size.width as f32)
+                    .with_height(ctx.screen_size.height as f32),
+            )
+            .build(&mut ctx.engine.user_interface.build_ctx());
+        }
+
+        let alpha = (self.opacity * 255.0) as u8;
+        ctx.engine
+            .user_interface
+            .send_message(WidgetMessage::background(
+                self.overlay,
+                MessageDirection::ToWidget,
+                Brush::Solid(Color::from_rgba(0, 0, 0, alpha)),
+            ));
+    }
+
+    /// Removes the overlay right away, e.g. when the state that owns the fade exits.
+    pub fn clear(&mut self, ctx: &mut GameContext) {
+        if self.overlay.is_some() {
+            ctx.engine
+                .user_interface
+                .send_message(WidgetMessage::remove(
+                    self.overlay,
+                    MessageDirection::ToWidget,
+                ));
+            self.overlay = Handle::NONE;
+        }
+        self.opacity = 0.0;
+        self.target = 0.0;
+    }
+}
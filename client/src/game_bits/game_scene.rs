@@ -0,0 +1,386 @@
+// The level that is being played, once it has been added to the engine: its scene, the camera
+// we look through and a model for each player in it. Game logic that runs on the level goes into
+// `GameScene::update`, which the main loop calls at the fixed time step. The camera either is the
+// local player's eyes or orbits them, see `camera_mode`, unless a spectator camera took over.
+// Frames are drawn between steps, so the camera is blended between its last two positions.
+
+use rg3d::{
+    core::{
+        algebra::{Matrix4, UnitQuaternion, Vector3},
+        pool::Handle,
+    },
+    scene::{
+        base::BaseBuilder,
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData},
+            MeshBuilder,
+        },
+        node::Node,
+        transform::TransformBuilder,
+        Scene,
+    },
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use super::{
+    app::GameEngine,
+    camera::{CameraMode, FirstPersonCamera, FreeFlyCamera, ThirdPersonCamera},
+    input::{Action, Input},
+    level::SpawnPoint,
+    scene_builder::{find_camera, LoadedScene},
+};
+
+const PLAYER_RADIUS: f32 = 0.3;
+const PLAYER_HEIGHT: f32 = 1.8;
+// Height of the eyes above the center of the player.
+const EYE_HEIGHT: f32 = 0.7;
+// Meters per second.
+const WALK_SPEED: f32 = 4.0;
+const JUMP_SPEED: f32 = 5.0;
+const GRAVITY: f32 = 9.81;
+
+// Where the camera is after a step.
+#[derive(Clone, Copy)]
+struct CameraPose {
+    position: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+}
+
+/// Cameras that detach from the local player, who stands still in the meantime.
+pub enum Spectator {
+    /// Flying around freely.
+    FreeFly(FreeFlyCamera),
+    /// Looking out of another player's eyes, by client id.
+    Player(u32),
+}
+
+pub struct GameScene {
+    pub scene: Handle<Scene>,
+    pub camera: Handle<Node>,
+    /// Models of the players in the level by client id, the local player included.
+    pub players: HashMap<u32, Handle<Node>>,
+    /// Client id of the player the camera belongs to, None while just looking at the level.
+    pub local_player: Option<u32>,
+    /// Where the local player looks, feed it mouse motion. The third-person camera turns with it
+    /// too.
+    pub look: FirstPersonCamera,
+    /// Change with `set_camera_mode`.
+    camera_mode: CameraMode,
+    /// Feed it the mouse wheel.
+    pub orbit: ThirdPersonCamera,
+    /// Change with `toggle_free_fly` and `spectate_next_player`.
+    spectator: Option<Spectator>,
+    /// The local player stands still, e.g. while the pointer isn't locked.
+    pub paused: bool,
+    // Until players collide with the level they walk on the height they spawned at.
+    ground_height: f32,
+    vertical_speed: f32,
+    spawn_points: Vec<SpawnPoint>,
+    // Players are spread over the spawn points in turn.
+    next_spawn_point: usize,
+    // The camera after the last two steps, frames are drawn in between. None after the camera
+    // jumped, it shouldn't glide over from where it was.
+    previous_pose: Option<CameraPose>,
+    current_pose: Option<CameraPose>,
+}
+
+impl GameScene {
+    /// Adds a freshly loaded level to the engine.
+    pub fn new(engine: &mut GameEngine, loaded: LoadedScene) -> Self {
+        let camera = find_camera(&loaded.scene);
+        let scene = engine.scenes.add(loaded.scene);
+
+        Self {
+            scene,
+            camera,
+            players: HashMap::new(),
+            local_player: None,
+            look: FirstPersonCamera::default(),
+            camera_mode: CameraMode::FirstPerson,
+            orbit: ThirdPersonCamera::default(),
+            spectator: None,
+            paused: false,
+            ground_height: 0.0,
+            vertical_speed: 0.0,
+            spawn_points: loaded.spawn_points,
+            next_spawn_point: 0,
+            previous_pose: None,
+            current_pose: None,
+        }
+    }
+
+    // Editor scenes don't have spawn points, their players start where the camera is.
+    fn next_spawn(&mut self, engine: &GameEngine) -> SpawnPoint {
+        if self.spawn_points.is_empty() {
+            let position = engine.scenes[self.scene].graph[self.camera].global_position();
+            return SpawnPoint {
+                position: [position.x, position.y - EYE_HEIGHT, position.z],
+                yaw: 0.0,
+            };
+        }
+
+        let spawn_point = self.spawn_points[self.next_spawn_point % self.spawn_points.len()];
+        self.next_spawn_point += 1;
+        spawn_point
+    }
+
+    /// Puts a model for `client_id` at the next spawn point, replacing the one it already had.
+    pub fn spawn_player(&mut self, engine: &mut GameEngine, client_id: u32) -> Handle<Node> {
+        self.remove_player(engine, client_id);
+
+        let spawn_point = self.next_spawn(engine);
+        // The cylinder starts at its origin, move it down so the node is the player's center.
+        let surface_data = SurfaceData::make_cylinder(
+            16,
+            PLAYER_RADIUS,
+            PLAYER_HEIGHT,
+            true,
+            Matrix4::new_translation(&Vector3::new(0.0, -PLAYER_HEIGHT * 0.5, 0.0)),
+        );
+        let player = MeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::from(spawn_point.position))
+                    .with_local_rotation(UnitQuaternion::from_axis_angle(
+                        &Vector3::y_axis(),
+                        spawn_point.yaw.to_radians(),
+                    ))
+                    .build(),
+            ),
+        )
+        .with_surfaces(vec![SurfaceBuilder::new(Arc::new(RwLock::new(
+            surface_data,
+        )))
+        .build()])
+        .build(&mut engine.scenes[self.scene].graph);
+
+        self.players.insert(client_id, player);
+
+        self.update_visibility(engine);
+        if self.local_player == Some(client_id) {
+            self.look = FirstPersonCamera::new(spawn_point.yaw);
+            self.orbit.snap();
+            self.snap_camera();
+            self.ground_height = spawn_point.position[1];
+            self.vertical_speed = 0.0;
+        }
+
+        player
+    }
+
+    pub fn camera_mode(&self) -> CameraMode {
+        self.camera_mode
+    }
+
+    pub fn set_camera_mode(&mut self, engine: &mut GameEngine, mode: CameraMode) {
+        self.camera_mode = mode;
+        self.orbit.snap();
+        self.snap_camera();
+        self.update_visibility(engine);
+    }
+
+    pub fn spectator(&self) -> Option<&Spectator> {
+        self.spectator.as_ref()
+    }
+
+    fn set_spectator(&mut self, engine: &mut GameEngine, spectator: Option<Spectator>) {
+        self.spectator = spectator;
+        self.orbit.snap();
+        self.snap_camera();
+        self.update_visibility(engine);
+    }
+
+    /// Detaches a noclip camera where the camera is now, or gives the camera back to the local
+    /// player.
+    pub fn toggle_free_fly(&mut self, engine: &mut GameEngine) {
+        let spectator = match self.spectator {
+            Some(Spectator::FreeFly(_)) => None,
+            _ => {
+                let position = engine.scenes[self.scene].graph[self.camera].global_position();
+                Some(Spectator::FreeFly(FreeFlyCamera::new(position, self.look)))
+            }
+        };
+        self.set_spectator(engine, spectator);
+    }
+
+    /// Looks out of the eyes of the next player after the one watched now, in client id order,
+    /// and back to our own after the last one. Returns who is watched.
+    pub fn spectate_next_player(&mut self, engine: &mut GameEngine) -> Option<u32> {
+        let mut others = self
+            .players
+            .keys()
+            .copied()
+            .filter(|id| Some(*id) != self.local_player)
+            .collect::<Vec<u32>>();
+        others.sort_unstable();
+
+        let next = match self.spectator {
+            Some(Spectator::Player(watched)) => others.into_iter().find(|id| *id > watched),
+            _ => others.first().copied(),
+        };
+        self.set_spectator(engine, next.map(Spectator::Player));
+        next
+    }
+
+    // We look out of our own model in first person and out of the watched player's while
+    // spectating, either would only get in the way.
+    fn update_visibility(&self, engine: &mut GameEngine) {
+        let graph = &mut engine.scenes[self.scene].graph;
+        for (&client_id, &player) in self.players.iter() {
+            let visible = match self.spectator {
+                Some(Spectator::FreeFly(_)) => true,
+                Some(Spectator::Player(watched)) => client_id != watched,
+                None => {
+                    Some(client_id) != self.local_player
+                        || self.camera_mode != CameraMode::FirstPerson
+                }
+            };
+            graph[player].set_visibility(visible);
+        }
+    }
+
+    /// What mouse motion turns, None while watching another player.
+    pub fn look_mut(&mut self) -> Option<&mut FirstPersonCamera> {
+        match self.spectator.as_mut() {
+            Some(Spectator::FreeFly(free_fly)) => Some(&mut free_fly.look),
+            Some(Spectator::Player(_)) => None,
+            None => Some(&mut self.look),
+        }
+    }
+
+    pub fn remove_player(&mut self, engine: &mut GameEngine, client_id: u32) {
+        if let Some(player) = self.players.remove(&client_id) {
+            engine.scenes[self.scene].graph.remove_node(player);
+        }
+    }
+
+    // Where the local player wants to go, relative to where they look. At most unit length,
+    // shorter when a stick is only tilted part of the way.
+    fn walk_direction(&self, input: &Input) -> Vector3<f32> {
+        let rotation = self.look.body_rotation();
+        let forward = rotation * Vector3::z();
+        // Looking down +Z, +X is on the left.
+        let left = rotation * Vector3::x();
+
+        let (right_amount, forward_amount) = input.move_vector();
+        let direction = forward * forward_amount - left * right_amount;
+        // Diagonals aren't faster.
+        if direction.norm() > 1.0 {
+            direction.normalize()
+        } else {
+            direction
+        }
+    }
+
+    /// Called once per fixed time step.
+    pub fn update(&mut self, engine: &mut GameEngine, input: &Input, dt: f32) {
+        // They left.
+        if let Some(Spectator::Player(watched)) = self.spectator {
+            if !self.players.contains_key(&watched) {
+                self.set_spectator(engine, None);
+            }
+        }
+
+        let paused = self.paused;
+        let graph = &mut engine.scenes[self.scene].graph;
+        match self.spectator.as_mut() {
+            Some(Spectator::FreeFly(free_fly)) => {
+                if !paused {
+                    free_fly.update(input, dt);
+                }
+                graph[self.camera]
+                    .local_transform_mut()
+                    .set_position(free_fly.position())
+                    .set_rotation(free_fly.rotation());
+            }
+            Some(&mut Spectator::Player(watched)) => {
+                // Only where the body faces is known about other players, not their pitch.
+                let player = self.players[&watched];
+                let eyes = graph[player].global_position() + Vector3::new(0.0, EYE_HEIGHT, 0.0);
+                let rotation = *graph[player].local_transform().rotation();
+                graph[self.camera]
+                    .local_transform_mut()
+                    .set_position(eyes)
+                    .set_rotation(rotation);
+            }
+            None => self.update_local_player(engine, input, dt),
+        }
+
+        let transform = engine.scenes[self.scene].graph[self.camera].local_transform();
+        let pose = CameraPose {
+            position: *transform.position(),
+            rotation: *transform.rotation(),
+        };
+        self.previous_pose = Some(self.current_pose.unwrap_or(pose));
+        self.current_pose = Some(pose);
+    }
+
+    // Draws the camera where it is now, instead of blending over from its previous step.
+    fn snap_camera(&mut self) {
+        self.previous_pose = None;
+        self.current_pose = None;
+    }
+
+    /// Puts the camera `alpha` (0 to 1) of the way from its previous step to the last one, call
+    /// right before rendering with `GameClock::alpha`. The next step sets it properly again.
+    pub fn interpolate_camera(&self, engine: &mut GameEngine, alpha: f32) {
+        if let (Some(from), Some(to)) = (self.previous_pose, self.current_pose) {
+            let graph = &mut engine.scenes[self.scene].graph;
+            graph[self.camera]
+                .local_transform_mut()
+                .set_position(from.position.lerp(&to.position, alpha))
+                .set_rotation(
+                    from.rotation
+                        .try_slerp(&to.rotation, alpha, f32::EPSILON)
+                        .unwrap_or(to.rotation),
+                );
+            // Global transforms were worked out by the last engine update, before this.
+            graph.update_hierarchical_data();
+        }
+    }
+
+    fn update_local_player(&mut self, engine: &mut GameEngine, input: &Input, dt: f32) {
+        let player = match self.local_player.and_then(|id| self.players.get(&id)) {
+            Some(player) if !self.paused => *player,
+            _ => return,
+        };
+        let graph = &mut engine.scenes[self.scene].graph;
+
+        let mut position = *graph[player].local_transform().position();
+        position += self.walk_direction(input) * WALK_SPEED * dt;
+
+        let on_ground = position.y <= self.ground_height;
+        if on_ground && input.was_action_pressed(Action::Jump) {
+            self.vertical_speed = JUMP_SPEED;
+        }
+        self.vertical_speed -= GRAVITY * dt;
+        position.y += self.vertical_speed * dt;
+        if position.y <= self.ground_height {
+            position.y = self.ground_height;
+            self.vertical_speed = 0.0;
+        }
+
+        // The body turns along with where the player looks.
+        graph[player]
+            .local_transform_mut()
+            .set_position(position)
+            .set_rotation(self.look.body_rotation());
+
+        let eyes = position + Vector3::new(0.0, EYE_HEIGHT, 0.0);
+        let camera_position = match self.camera_mode {
+            CameraMode::FirstPerson => eyes,
+            CameraMode::ThirdPerson => {
+                self.orbit
+                    .update(&engine.scenes[self.scene], eyes, self.look.rotation(), dt)
+            }
+        };
+        engine.scenes[self.scene].graph[self.camera]
+            .local_transform_mut()
+            .set_position(camera_position)
+            .set_rotation(self.look.rotation());
+    }
+}
@@ -0,0 +1,265 @@
+// Gamepads, as far as the platform can see them. The browser's Gamepad API has no events worth
+// relying on, so pads are polled once per time step and connects and disconnects are found by
+// comparing with the previous poll. Only pads with the standard layout are used, their buttons
+// feed the same actions as the keyboard and the sticks walk and look around.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+use super::input::Action;
+
+/// One poll of a connected pad.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GamepadState {
+    pub index: u32,
+    pub name: String,
+    /// Standard layout: left stick x and y, then right stick x and y. Down is positive y.
+    pub axes: Vec<f32>,
+    /// How far each button is pressed, 0 to 1.
+    pub buttons: Vec<f32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GamepadSettings {
+    /// How far a stick has to be tilted before it does anything, 0 to 1. Worn sticks don't
+    /// center exactly and would drift without it.
+    pub deadzone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self { deadzone: 0.15 }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum GamepadEvent {
+    Connected(String),
+    Disconnected(String),
+}
+
+// Button indices of the standard layout.
+const BUTTON_ACTIONS: &[(usize, Action)] = &[
+    (0, Action::Jump),
+    // Start pauses like Escape does.
+    (9, Action::Menu),
+    (12, Action::MoveForward),
+    (13, Action::MoveBackward),
+    (14, Action::StrafeLeft),
+    (15, Action::StrafeRight),
+];
+
+// Analog triggers report a value for buttons, pressing halfway counts.
+const BUTTON_THRESHOLD: f32 = 0.5;
+
+/// Stick position with the deadzone cut out and the rest stretched back to the full range, so
+/// the stick still goes from just past the deadzone smoothly up to 1.
+pub fn apply_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let length = (x * x + y * y).sqrt();
+    if length <= deadzone {
+        return (0.0, 0.0);
+    }
+
+    let scaled = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    (x / length * scaled, y / length * scaled)
+}
+
+#[derive(Default)]
+pub struct Gamepads {
+    pads: Vec<GamepadState>,
+    down: HashSet<Action>,
+    // Went down in this time step.
+    pressed: HashSet<Action>,
+    // Connects and disconnects found in this time step.
+    events: Vec<GamepadEvent>,
+}
+
+impl Gamepads {
+    /// Call at the start of each time step with the pads the platform sees.
+    pub fn update(&mut self, pads: Vec<GamepadState>) {
+        for pad in pads.iter() {
+            if !self.pads.iter().any(|old| old.index == pad.index) {
+                self.events.push(GamepadEvent::Connected(pad.name.clone()));
+            }
+        }
+        for old in self.pads.iter() {
+            if !pads.iter().any(|pad| pad.index == old.index) {
+                self.events
+                    .push(GamepadEvent::Disconnected(old.name.clone()));
+            }
+        }
+
+        let mut down = HashSet::new();
+        for pad in pads.iter() {
+            for (button, action) in BUTTON_ACTIONS.iter() {
+                if pad.buttons.get(*button).copied().unwrap_or(0.0) >= BUTTON_THRESHOLD {
+                    down.insert(*action);
+                }
+            }
+        }
+        self.pressed = down.difference(&self.down).copied().collect();
+        self.down = down;
+        self.pads = pads;
+    }
+
+    /// Call at the end of each time step.
+    pub fn end_step(&mut self) {
+        self.pressed.clear();
+        self.events.clear();
+    }
+
+    pub fn is_action_down(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    pub fn was_action_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Connects and disconnects found in this time step.
+    pub fn events(&self) -> &[GamepadEvent] {
+        &self.events
+    }
+
+    // The first pad whose stick is tilted gets to steer, several pads at once would fight.
+    fn stick(&self, first_axis: usize, settings: &GamepadSettings) -> (f32, f32) {
+        self.pads
+            .iter()
+            .map(|pad| {
+                let axis = |index: usize| pad.axes.get(index).copied().unwrap_or(0.0);
+                apply_deadzone(axis(first_axis), axis(first_axis + 1), settings.deadzone)
+            })
+            .find(|stick| *stick != (0.0, 0.0))
+            .unwrap_or((0.0, 0.0))
+    }
+
+    pub fn left_stick(&self, settings: &GamepadSettings) -> (f32, f32) {
+        self.stick(0, settings)
+    }
+
+    pub fn right_stick(&self, settings: &GamepadSettings) -> (f32, f32) {
+        self.stick(2, settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad(index: u32, name: &str) -> GamepadState {
+        GamepadState {
+            index,
+            name: name.to_string(),
+            axes: vec![0.0; 4],
+            buttons: vec![0.0; 17],
+        }
+    }
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5
+    }
+
+    #[test]
+    fn deadzone_cuts_small_tilts() {
+        assert_eq!(apply_deadzone(0.0, 0.0, 0.15), (0.0, 0.0));
+        assert_eq!(apply_deadzone(0.1, -0.1, 0.15), (0.0, 0.0));
+        assert_eq!(apply_deadzone(0.15, 0.0, 0.15), (0.0, 0.0));
+    }
+
+    #[test]
+    fn deadzone_rescales_the_rest() {
+        // Halfway between the deadzone and the edge is half the range.
+        assert!(close(apply_deadzone(0.6, 0.0, 0.2), (0.5, 0.0)));
+        assert!(close(apply_deadzone(0.0, -1.0, 0.2), (0.0, -1.0)));
+        // The direction is kept.
+        let (x, y) = apply_deadzone(0.6, 0.8, 0.2);
+        assert!(close((x, y), (0.6, 0.8)));
+    }
+
+    #[test]
+    fn deadzone_clamps_corners() {
+        // Square stick gates report more than unit length in the corners.
+        let (x, y) = apply_deadzone(1.0, 1.0, 0.15);
+        assert!(((x * x + y * y).sqrt() - 1.0).abs() < 1e-5);
+        assert!((x - y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn connects_and_disconnects() {
+        let mut gamepads = Gamepads::default();
+        gamepads.update(vec![pad(0, "first"), pad(1, "second")]);
+        let connected = gamepads
+            .events()
+            .iter()
+            .filter(|event| matches!(event, GamepadEvent::Connected(_)))
+            .count();
+        assert_eq!(connected, 2);
+        gamepads.end_step();
+        assert!(gamepads.events().is_empty());
+
+        // Polls without changes are quiet.
+        gamepads.update(vec![pad(0, "first"), pad(1, "second")]);
+        assert!(gamepads.events().is_empty());
+        gamepads.end_step();
+
+        gamepads.update(vec![pad(1, "second")]);
+        match gamepads.events() {
+            [GamepadEvent::Disconnected(name)] => assert_eq!(name, "first"),
+            events => panic!("unexpected events {:?}", events),
+        }
+        gamepads.end_step();
+
+        // A pad coming back in another slot is a new pad.
+        gamepads.update(vec![pad(1, "second"), pad(2, "first")]);
+        match gamepads.events() {
+            [GamepadEvent::Connected(name)] => assert_eq!(name, "first"),
+            events => panic!("unexpected events {:?}", events),
+        }
+    }
+
+    #[test]
+    fn buttons_are_pressed_once() {
+        let mut gamepads = Gamepads::default();
+        let mut held = pad(0, "pad");
+        held.buttons[9] = 1.0;
+
+        gamepads.update(vec![held.clone()]);
+        assert!(gamepads.is_action_down(Action::Menu));
+        assert!(gamepads.was_action_pressed(Action::Menu));
+        gamepads.end_step();
+
+        gamepads.update(vec![held]);
+        assert!(gamepads.is_action_down(Action::Menu));
+        assert!(!gamepads.was_action_pressed(Action::Menu));
+        gamepads.end_step();
+
+        gamepads.update(vec![pad(0, "pad")]);
+        assert!(!gamepads.is_action_down(Action::Menu));
+    }
+
+    #[test]
+    fn half_pressed_triggers_count() {
+        let mut gamepads = Gamepads::default();
+        let mut pressed = pad(0, "pad");
+        pressed.buttons[0] = 0.4;
+        gamepads.update(vec![pressed.clone()]);
+        assert!(!gamepads.is_action_down(Action::Jump));
+
+        pressed.buttons[0] = 0.5;
+        gamepads.update(vec![pressed]);
+        assert!(gamepads.is_action_down(Action::Jump));
+    }
+
+    #[test]
+    fn first_tilted_stick_steers() {
+        let settings = GamepadSettings::default();
+        let mut gamepads = Gamepads::default();
+        let mut second = pad(1, "second");
+        second.axes = vec![0.0, -1.0, 1.0, 0.0];
+        gamepads.update(vec![pad(0, "first"), second]);
+
+        assert!(close(gamepads.left_stick(&settings), (0.0, -1.0)));
+        assert!(close(gamepads.right_stick(&settings), (1.0, 0.0)));
+    }
+}
@@ -0,0 +1,275 @@
+// Development mode: the client polls the asset manifest and reloads whatever changed in the
+// running game, without touching the connection to the server. Keep
+// `cargo run --bin manifest -- --watch` running next to it, so saving an asset updates its hash.
+// Changed textures are swapped in the scene, a changed model or level file rebuilds the level.
+
+use rg3d::{
+    core::pool::Handle,
+    gui::{
+        message::{MessageDirection, TextMessage},
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        HorizontalAlignment, Thickness, VerticalAlignment,
+    },
+    resource::texture::Texture,
+    scene::{node::Node, Scene},
+};
+
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    app::{GameContext, UiNode},
+    assets::{AssetError, AssetKind, AssetTracker, Manifest},
+    level_loader::load_level_scene,
+    scene_builder::LoadedScene,
+    scene_manager::swap_scene,
+    skybox::{set_face_wrap, SkyboxDefinition},
+};
+
+// Seconds between two looks at the manifest.
+const POLL_INTERVAL: f32 = 1.0;
+
+type Pending<T> = Arc<Mutex<Option<T>>>;
+
+// A changed texture, the version the scene uses and the new one.
+struct TextureSwap {
+    old: Texture,
+    new: Texture,
+    // The skybox it is a face of, the new version needs its wrap modes.
+    skybox: Option<SkyboxDefinition>,
+}
+
+pub struct HotReload {
+    status_text: Handle<UiNode>,
+    poll_timer: f32,
+    manifest: Option<Pending<Result<Manifest, AssetError>>>,
+    level: Option<Pending<Result<LoadedScene, AssetError>>>,
+    textures: Arc<Mutex<Vec<TextureSwap>>>,
+}
+
+impl HotReload {
+    pub fn new(ctx: &mut GameContext) -> Self {
+        let status_text = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_margin(Thickness::uniform(4.0))
+                .with_horizontal_alignment(HorizontalAlignment::Right)
+                .with_vertical_alignment(VerticalAlignment::Top),
+        )
+        .with_text("hot reload on")
+        .build(&mut ctx.engine.user_interface.build_ctx());
+
+        Self {
+            status_text,
+            poll_timer: 0.0,
+            manifest: None,
+            level: None,
+            textures: Default::default(),
+        }
+    }
+
+    fn show_status(&self, ctx: &mut GameContext, text: String) {
+        ctx.engine.user_interface.send_message(TextMessage::text(
+            self.status_text,
+            MessageDirection::ToWidget,
+            format!("hot reload: {}", text),
+        ));
+    }
+
+    /// Call once per time step.
+    pub fn update(&mut self, ctx: &mut GameContext) {
+        self.poll_timer += ctx.clock.timestep();
+        if self.poll_timer >= POLL_INTERVAL && self.manifest.is_none() && self.level.is_none() {
+            self.poll_timer = 0.0;
+            self.poll_manifest(ctx);
+        }
+
+        if let Some(result) = take_pending(&mut self.manifest) {
+            match result {
+                Ok(manifest) => self.apply_manifest(ctx, manifest),
+                Err(err) => self.show_status(ctx, err.to_string()),
+            }
+        }
+
+        let swaps = mem::take(&mut *self.textures.lock().unwrap());
+        if !swaps.is_empty() {
+            if let Some(game_scene) = ctx.scene.as_ref() {
+                swap_textures(&mut ctx.engine.scenes[game_scene.scene], &swaps);
+                self.show_status(ctx, format!("reloaded {} textures", swaps.len()));
+            }
+        }
+
+        if let Some(result) = take_pending(&mut self.level) {
+            match result {
+                Ok(loaded) => {
+                    swap_scene(ctx, loaded);
+                    self.show_status(ctx, "reloaded the level".to_string());
+                }
+                // Keep playing the old version until the level is fixed.
+                Err(err) => self.show_status(ctx, err.to_string()),
+            }
+        }
+    }
+
+    fn poll_manifest(&mut self, ctx: &mut GameContext) {
+        let pending = Pending::default();
+        self.manifest = Some(pending.clone());
+
+        let url = ctx.platform.uncached_url(&ctx.config.manifest_url);
+        let hashed_urls = ctx.config.hashed_asset_urls;
+        ctx.platform.spawn(Box::pin(async move {
+            let result = Manifest::load(&AssetTracker::new(), &url, hashed_urls).await;
+            *pending.lock().unwrap() = Some(result);
+        }));
+    }
+
+    fn apply_manifest(&mut self, ctx: &mut GameContext, manifest: Manifest) {
+        let old = ctx.manifest.clone();
+        let new = Arc::new(manifest);
+
+        let changed = new
+            .assets
+            .iter()
+            .filter(|(id, entry)| {
+                old.assets
+                    .get(*id)
+                    .map_or(true, |old_entry| old_entry.hash != entry.hash)
+            })
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<String>>();
+        if changed.is_empty() {
+            return;
+        }
+
+        ctx.manifest = new.clone();
+
+        // Still loading, that picks up the new manifest anyway.
+        if ctx.scene.is_none() {
+            return;
+        }
+
+        self.show_status(ctx, format!("{} changed", changed.join(", ")));
+
+        // Without hashes in the urls a changed file keeps its url, and the resource manager would
+        // hand out the version it already has.
+        let reload_in_place = changed
+            .iter()
+            .any(|id| old.url(id).ok() == new.url(id).ok());
+        let rebuild_level = changed
+            .iter()
+            .any(|id| new.assets[id].kind != AssetKind::Texture);
+        let skybox_of = |id: &str| {
+            new.skyboxes
+                .values()
+                .find(|skybox| skybox.faces().contains(&id))
+                .cloned()
+        };
+        let texture_urls = changed
+            .iter()
+            .filter(|id| new.assets[*id].kind == AssetKind::Texture)
+            .filter_map(|id| Some((old.url(id).ok()?, new.url(id).ok()?, skybox_of(id))))
+            .collect::<Vec<(String, String, Option<SkyboxDefinition>)>>();
+
+        let level = if rebuild_level {
+            let pending = Pending::default();
+            self.level = Some(pending.clone());
+            Some(pending)
+        } else {
+            None
+        };
+        let level_id = ctx.level.clone();
+        let textures = self.textures.clone();
+        let resource_manager = ctx.engine.resource_manager.clone();
+
+        ctx.platform.spawn(Box::pin(async move {
+            if reload_in_place {
+                resource_manager.reload_resources().await;
+                // Same textures, but reloading reset the wrap modes of skybox faces.
+                for (_, url, skybox) in texture_urls {
+                    if skybox.is_some() {
+                        let texture = resource_manager.request_texture(&url);
+                        textures.lock().unwrap().push(TextureSwap {
+                            old: texture.clone(),
+                            new: texture,
+                            skybox,
+                        });
+                    }
+                }
+            } else {
+                for (old_url, new_url, skybox) in texture_urls {
+                    // Already loaded, so this is the same texture the scene uses.
+                    let old_texture = resource_manager.request_texture(&old_url);
+                    if let Ok(new_texture) = resource_manager.request_texture(&new_url).await {
+                        textures.lock().unwrap().push(TextureSwap {
+                            old: old_texture,
+                            new: new_texture,
+                            skybox,
+                        });
+                    }
+                }
+            }
+
+            if let Some(level) = level {
+                let result =
+                    load_level_scene(resource_manager, AssetTracker::new(), new, level_id).await;
+                *level.lock().unwrap() = Some(result);
+            }
+        }));
+    }
+}
+
+fn take_pending<T>(pending: &mut Option<Pending<T>>) -> Option<T> {
+    let value = pending.as_ref()?.lock().unwrap().take();
+    if value.is_some() {
+        *pending = None;
+    }
+    value
+}
+
+// Puts the new version of each texture wherever the old one is used.
+fn swap_textures(scene: &mut Scene, swaps: &[TextureSwap]) {
+    let replace = |texture: &mut Option<Texture>| {
+        if let Some(swap) = swaps
+            .iter()
+            .find(|swap| texture.as_ref() == Some(&swap.old))
+        {
+            if let Some(skybox) = swap.skybox.as_ref() {
+                set_face_wrap(&swap.new, skybox);
+            }
+            *texture = Some(swap.new.clone());
+        }
+    };
+
+    let nodes = scene
+        .graph
+        .traverse_handle_iter(scene.graph.get_root())
+        .collect::<Vec<Handle<Node>>>();
+    for handle in nodes {
+        match &mut scene.graph[handle] {
+            Node::Mesh(mesh) => {
+                for surface in mesh.surfaces_mut() {
+                    let mut diffuse = surface.diffuse_texture();
+                    replace(&mut diffuse);
+                    surface.set_diffuse_texture(diffuse);
+
+                    let mut normal = surface.normal_texture();
+                    replace(&mut normal);
+                    surface.set_normal_texture(normal);
+                }
+            }
+            Node::Camera(camera) => {
+                if let Some(skybox) = camera.skybox_mut() {
+                    replace(&mut skybox.front);
+                    replace(&mut skybox.back);
+                    replace(&mut skybox.left);
+                    replace(&mut skybox.right);
+                    replace(&mut skybox.top);
+                    replace(&mut skybox.bottom);
+                }
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+// Keyboard, gamepad and touch state for game code. Keys are mapped to named actions through a
+// table that can be rebound (see key_bindings.rs), gamepad buttons and the touch controls feed
+// the same actions, so game code asks "is the player moving forward" instead of "is W down".
+//
+// Key presses are remembered until the end of the next fixed time step, so a quick tap between
+// two steps still shows up in `was_action_pressed` exactly once.
+
+use rg3d::event::{ElementState, Event, VirtualKeyCode, WindowEvent};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+use super::{
+    gamepad::{GamepadSettings, Gamepads},
+    key_bindings::{KeyBindings, CANCEL_KEY},
+    touch::{TouchEvent, TouchInput},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    Jump,
+    Chat,
+    Menu,
+    FlyUp,
+    FlyDown,
+    FlyFast,
+    // Debug keys, bound like the rest so nothing else can be bound to them by accident.
+    NextSkybox,
+    NextLevel,
+    ToggleFullscreen,
+    NextCameraMode,
+    ToggleFreeFly,
+    SpectateNextPlayer,
+    SaveRecording,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Jump,
+        Action::Chat,
+        Action::Menu,
+        Action::FlyUp,
+        Action::FlyDown,
+        Action::FlyFast,
+        Action::NextSkybox,
+        Action::NextLevel,
+        Action::ToggleFullscreen,
+        Action::NextCameraMode,
+        Action::ToggleFreeFly,
+        Action::SpectateNextPlayer,
+        Action::SaveRecording,
+    ];
+
+    /// Name for the controls screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::StrafeLeft => "Strafe left",
+            Action::StrafeRight => "Strafe right",
+            Action::Jump => "Jump",
+            Action::Chat => "Chat",
+            Action::Menu => "Menu",
+            Action::FlyUp => "Fly up",
+            Action::FlyDown => "Fly down",
+            Action::FlyFast => "Fly faster",
+            Action::NextSkybox => "Next skybox",
+            Action::NextLevel => "Next level",
+            Action::ToggleFullscreen => "Full screen",
+            Action::NextCameraMode => "First or third person",
+            Action::ToggleFreeFly => "Fly around",
+            Action::SpectateNextPlayer => "Watch other players",
+            Action::SaveRecording => "Save input recording",
+        }
+    }
+
+    pub fn default_key(self) -> VirtualKeyCode {
+        match self {
+            Action::MoveForward => VirtualKeyCode::W,
+            Action::MoveBackward => VirtualKeyCode::S,
+            Action::StrafeLeft => VirtualKeyCode::A,
+            Action::StrafeRight => VirtualKeyCode::D,
+            Action::Jump => VirtualKeyCode::Space,
+            Action::Chat => VirtualKeyCode::Return,
+            Action::Menu => VirtualKeyCode::Escape,
+            Action::FlyUp => VirtualKeyCode::E,
+            Action::FlyDown => VirtualKeyCode::Q,
+            Action::FlyFast => VirtualKeyCode::LShift,
+            Action::NextSkybox => VirtualKeyCode::F2,
+            Action::NextLevel => VirtualKeyCode::F3,
+            Action::ToggleFullscreen => VirtualKeyCode::F4,
+            Action::NextCameraMode => VirtualKeyCode::F5,
+            Action::ToggleFreeFly => VirtualKeyCode::F6,
+            Action::SpectateNextPlayer => VirtualKeyCode::F7,
+            Action::SaveRecording => VirtualKeyCode::F9,
+        }
+    }
+}
+
+/// What ended a key capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapturedKey {
+    Key(VirtualKeyCode),
+    /// `CANCEL_KEY` was pressed, it can't be captured itself.
+    Cancelled,
+}
+
+#[derive(Default)]
+pub struct Input {
+    pub bindings: KeyBindings,
+    pub gamepads: Gamepads,
+    pub gamepad_settings: GamepadSettings,
+    pub touch: TouchInput,
+    down: HashSet<VirtualKeyCode>,
+    // Went down since the last time step.
+    pressed: HashSet<VirtualKeyCode>,
+    // While capturing, the next key press is kept for the controls screen and triggers nothing.
+    capturing: bool,
+    captured: Option<CapturedKey>,
+    // In pixels, None while outside the window.
+    cursor: Option<(f32, f32)>,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed it every event from the event loop.
+    pub fn handle_event(&mut self, event: &Event<()>) {
+        if let Event::WindowEvent { event, .. } = event {
+            match event {
+                WindowEvent::KeyboardInput { input, .. } => {
+                    if let Some(key) = input.virtual_keycode {
+                        match input.state {
+                            ElementState::Pressed if self.capturing => {
+                                self.capturing = false;
+                                self.captured = Some(if key == CANCEL_KEY {
+                                    CapturedKey::Cancelled
+                                } else {
+                                    CapturedKey::Key(key)
+                                });
+                            }
+                            ElementState::Pressed => {
+                                // Key repeat sends more presses without releases in between.
+                                if self.down.insert(key) {
+                                    self.pressed.insert(key);
+                                }
+                            }
+                            ElementState::Released => {
+                                self.down.remove(&key);
+                            }
+                        }
+                    }
+                }
+                // Touch screens on native targets, the browser's touches come through
+                // `handle_touch`.
+                WindowEvent::Touch(touch) => self.handle_touch(TouchEvent {
+                    id: touch.id,
+                    phase: touch.phase,
+                    x: touch.location.x as f32,
+                    y: touch.location.y as f32,
+                }),
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.cursor = Some((position.x as f32, position.y as f32));
+                }
+                WindowEvent::CursorLeft { .. } => self.cursor = None,
+                // Releases that happen while another window has focus never reach us.
+                WindowEvent::Focused(false) => self.down.clear(),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn handle_touch(&mut self, event: TouchEvent) {
+        self.touch.handle(event);
+    }
+
+    /// Starts waiting for a key press, see `take_captured_key`.
+    pub fn capture_next_key(&mut self) {
+        self.capturing = true;
+        self.captured = None;
+    }
+
+    pub fn cancel_capture(&mut self) {
+        self.capturing = false;
+        self.captured = None;
+    }
+
+    /// The key pressed since `capture_next_key`, once there is one.
+    pub fn take_captured_key(&mut self) -> Option<CapturedKey> {
+        self.captured.take()
+    }
+
+    /// Call at the end of each time step.
+    pub fn end_step(&mut self) {
+        self.pressed.clear();
+        self.gamepads.end_step();
+        self.touch.end_step();
+    }
+
+    /// Where the mouse cursor is in the window, in pixels.
+    pub fn cursor_position(&self) -> Option<(f32, f32)> {
+        self.cursor
+    }
+
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.down.contains(&key)
+    }
+
+    pub fn is_action_down(&self, action: Action) -> bool {
+        (action == Action::Jump && self.touch.is_jump_down())
+            || self.gamepads.is_action_down(action)
+            || self
+                .down
+                .iter()
+                .any(|key| self.bindings.action(*key) == Some(action))
+    }
+
+    /// True for the one time step after a key of `action` went down.
+    pub fn was_action_pressed(&self, action: Action) -> bool {
+        (action == Action::Jump && self.touch.was_jump_pressed())
+            || self.gamepads.was_action_pressed(action)
+            || self
+                .pressed
+                .iter()
+                .any(|key| self.bindings.action(*key) == Some(action))
+    }
+
+    /// How much the player wants to strafe right and walk forward, each -1 to 1. Keys give all
+    /// or nothing, the left stick and the touch joystick anything in between.
+    pub fn move_vector(&self) -> (f32, f32) {
+        let axis = |positive: Action, negative: Action| {
+            let mut value = 0.0;
+            if self.is_action_down(positive) {
+                value += 1.0;
+            }
+            if self.is_action_down(negative) {
+                value -= 1.0;
+            }
+            value
+        };
+        let (pad_x, pad_y) = self.gamepads.left_stick(&self.gamepad_settings);
+        let (touch_x, touch_y) = self.touch.stick();
+        let (stick_x, stick_y) = (pad_x + touch_x, pad_y + touch_y);
+
+        let right = (axis(Action::StrafeRight, Action::StrafeLeft) + stick_x).clamp(-1.0, 1.0);
+        let forward = (axis(Action::MoveForward, Action::MoveBackward) - stick_y).clamp(-1.0, 1.0);
+        (right, forward)
+    }
+
+    /// Right stick, -1 to 1 on both axes with down being positive like mouse motion.
+    pub fn look_vector(&self) -> (f32, f32) {
+        self.gamepads.right_stick(&self.gamepad_settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rg3d::{
+        event::{DeviceId, KeyboardInput},
+        window::WindowId,
+    };
+
+    #[allow(deprecated)]
+    fn key(input: &mut Input, key: VirtualKeyCode, state: ElementState) {
+        input.handle_event(&Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::KeyboardInput {
+                device_id: unsafe { DeviceId::dummy() },
+                input: KeyboardInput {
+                    scancode: 0,
+                    state,
+                    virtual_keycode: Some(key),
+                    modifiers: Default::default(),
+                },
+                is_synthetic: false,
+            },
+        });
+    }
+
+    #[test]
+    fn presses_last_one_step() {
+        let mut input = Input::new();
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        key(&mut input, VirtualKeyCode::Space, ElementState::Released);
+        assert!(input.was_action_pressed(Action::Jump));
+        assert!(!input.is_action_down(Action::Jump));
+
+        input.end_step();
+        assert!(!input.was_action_pressed(Action::Jump));
+    }
+
+    #[test]
+    fn captured_keys_trigger_nothing() {
+        let mut input = Input::new();
+        input.capture_next_key();
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+
+        assert_eq!(
+            input.take_captured_key(),
+            Some(CapturedKey::Key(VirtualKeyCode::Space))
+        );
+        assert_eq!(input.take_captured_key(), None);
+        assert!(!input.was_action_pressed(Action::Jump));
+        assert!(!input.is_action_down(Action::Jump));
+    }
+
+    #[test]
+    fn cancel_key_is_not_captured() {
+        let mut input = Input::new();
+        input.capture_next_key();
+        key(&mut input, CANCEL_KEY, ElementState::Pressed);
+
+        assert_eq!(input.take_captured_key(), Some(CapturedKey::Cancelled));
+        // Nor does it open the menu it is bound to.
+        assert!(!input.was_action_pressed(Action::Menu));
+
+        // Capturing is over, the next press counts again.
+        key(&mut input, CANCEL_KEY, ElementState::Released);
+        key(&mut input, CANCEL_KEY, ElementState::Pressed);
+        assert!(input.was_action_pressed(Action::Menu));
+    }
+
+    #[test]
+    fn cancelled_capture_keeps_nothing() {
+        let mut input = Input::new();
+        input.capture_next_key();
+        input.cancel_capture();
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+
+        assert_eq!(input.take_captured_key(), None);
+        assert!(input.was_action_pressed(Action::Jump));
+    }
+}
@@ -0,0 +1,370 @@
+// Which key triggers which action. Bindings are plain data, saved with the platform's settings
+// as JSON so that rebinding survives a restart, e.g. {"move_forward": ["W"], "jump": []}.
+// Keys are saved by name, only the keys in the table below can be bound. Every action is saved,
+// an empty list means the player unbound it on purpose.
+
+use rg3d::event::VirtualKeyCode;
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::{input::Action, platform::Platform};
+
+/// Name of the bindings in the platform's settings.
+pub const SETTINGS_KEY: &str = "key_bindings";
+
+/// Cancels rebinding on the controls screen, so it is never captured as a new binding.
+pub const CANCEL_KEY: VirtualKeyCode = VirtualKeyCode::Escape;
+
+const KEY_NAMES: &[(VirtualKeyCode, &str)] = &[
+    (VirtualKeyCode::Key1, "1"),
+    (VirtualKeyCode::Key2, "2"),
+    (VirtualKeyCode::Key3, "3"),
+    (VirtualKeyCode::Key4, "4"),
+    (VirtualKeyCode::Key5, "5"),
+    (VirtualKeyCode::Key6, "6"),
+    (VirtualKeyCode::Key7, "7"),
+    (VirtualKeyCode::Key8, "8"),
+    (VirtualKeyCode::Key9, "9"),
+    (VirtualKeyCode::Key0, "0"),
+    (VirtualKeyCode::A, "A"),
+    (VirtualKeyCode::B, "B"),
+    (VirtualKeyCode::C, "C"),
+    (VirtualKeyCode::D, "D"),
+    (VirtualKeyCode::E, "E"),
+    (VirtualKeyCode::F, "F"),
+    (VirtualKeyCode::G, "G"),
+    (VirtualKeyCode::H, "H"),
+    (VirtualKeyCode::I, "I"),
+    (VirtualKeyCode::J, "J"),
+    (VirtualKeyCode::K, "K"),
+    (VirtualKeyCode::L, "L"),
+    (VirtualKeyCode::M, "M"),
+    (VirtualKeyCode::N, "N"),
+    (VirtualKeyCode::O, "O"),
+    (VirtualKeyCode::P, "P"),
+    (VirtualKeyCode::Q, "Q"),
+    (VirtualKeyCode::R, "R"),
+    (VirtualKeyCode::S, "S"),
+    (VirtualKeyCode::T, "T"),
+    (VirtualKeyCode::U, "U"),
+    (VirtualKeyCode::V, "V"),
+    (VirtualKeyCode::W, "W"),
+    (VirtualKeyCode::X, "X"),
+    (VirtualKeyCode::Y, "Y"),
+    (VirtualKeyCode::Z, "Z"),
+    (VirtualKeyCode::F1, "F1"),
+    (VirtualKeyCode::F2, "F2"),
+    (VirtualKeyCode::F3, "F3"),
+    (VirtualKeyCode::F4, "F4"),
+    (VirtualKeyCode::F5, "F5"),
+    (VirtualKeyCode::F6, "F6"),
+    (VirtualKeyCode::F7, "F7"),
+    (VirtualKeyCode::F8, "F8"),
+    (VirtualKeyCode::F9, "F9"),
+    (VirtualKeyCode::F10, "F10"),
+    (VirtualKeyCode::F11, "F11"),
+    (VirtualKeyCode::F12, "F12"),
+    (VirtualKeyCode::Escape, "Escape"),
+    (VirtualKeyCode::Tab, "Tab"),
+    (VirtualKeyCode::Space, "Space"),
+    (VirtualKeyCode::Return, "Enter"),
+    (VirtualKeyCode::Back, "Backspace"),
+    (VirtualKeyCode::Insert, "Insert"),
+    (VirtualKeyCode::Delete, "Delete"),
+    (VirtualKeyCode::Home, "Home"),
+    (VirtualKeyCode::End, "End"),
+    (VirtualKeyCode::PageUp, "Page Up"),
+    (VirtualKeyCode::PageDown, "Page Down"),
+    (VirtualKeyCode::Up, "Up"),
+    (VirtualKeyCode::Down, "Down"),
+    (VirtualKeyCode::Left, "Left"),
+    (VirtualKeyCode::Right, "Right"),
+    (VirtualKeyCode::LShift, "Left Shift"),
+    (VirtualKeyCode::RShift, "Right Shift"),
+    (VirtualKeyCode::LControl, "Left Ctrl"),
+    (VirtualKeyCode::RControl, "Right Ctrl"),
+    (VirtualKeyCode::LAlt, "Left Alt"),
+    (VirtualKeyCode::RAlt, "Right Alt"),
+    (VirtualKeyCode::Grave, "`"),
+    (VirtualKeyCode::Minus, "-"),
+    (VirtualKeyCode::Equals, "="),
+    (VirtualKeyCode::LBracket, "["),
+    (VirtualKeyCode::RBracket, "]"),
+    (VirtualKeyCode::Backslash, "\\"),
+    (VirtualKeyCode::Semicolon, ";"),
+    (VirtualKeyCode::Apostrophe, "'"),
+    (VirtualKeyCode::Comma, ","),
+    (VirtualKeyCode::Period, "."),
+    (VirtualKeyCode::Slash, "/"),
+];
+
+pub fn key_name(key: VirtualKeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(named, _)| *named == key)
+        .map(|(_, name)| *name)
+}
+
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, named)| *named == name)
+        .map(|(key, _)| *key)
+}
+
+/// A key triggers at most one action, an action can have any number of keys.
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    actions: HashMap<VirtualKeyCode, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut bindings = Self::empty();
+        for action in Action::ALL.iter() {
+            bindings.bind(action.default_key(), *action);
+        }
+        bindings
+    }
+}
+
+impl KeyBindings {
+    fn empty() -> Self {
+        Self {
+            actions: HashMap::new(),
+        }
+    }
+
+    /// Makes `key` trigger `action`, instead of whatever it triggered before.
+    pub fn bind(&mut self, key: VirtualKeyCode, action: Action) {
+        self.actions.insert(key, action);
+    }
+
+    pub fn unbind(&mut self, key: VirtualKeyCode) {
+        self.actions.remove(&key);
+    }
+
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.actions.get(&key).copied()
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = VirtualKeyCode> + '_ {
+        self.actions
+            .iter()
+            .filter(move |(_, bound)| **bound == action)
+            .map(|(key, _)| *key)
+    }
+
+    /// Names of the keys of `action` in a stable order, for showing them to the player.
+    pub fn key_names(&self, action: Action) -> Vec<&'static str> {
+        let mut names = self.keys(action).filter_map(key_name).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// The other action `key` is already used for, if any.
+    pub fn conflict(&self, key: VirtualKeyCode, action: Action) -> Option<Action> {
+        self.action(key).filter(|bound| *bound != action)
+    }
+
+    /// Makes `key` the only key of `action`. Fails with the conflicting action if `key` is used
+    /// for something else, the bindings stay as they were then.
+    pub fn rebind(&mut self, action: Action, key: VirtualKeyCode) -> Result<(), Action> {
+        if let Some(other) = self.conflict(key, action) {
+            return Err(other);
+        }
+
+        self.actions.retain(|_, bound| *bound != action);
+        self.bind(key, action);
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut data = Action::ALL
+            .iter()
+            .map(|action| (*action, Vec::new()))
+            .collect::<BTreeMap<Action, Vec<&str>>>();
+        for (key, action) in self.actions.iter() {
+            if let Some(name) = key_name(*key) {
+                data.entry(*action).or_default().push(name);
+            }
+        }
+        for names in data.values_mut() {
+            names.sort_unstable();
+        }
+        serde_json::to_string(&data).unwrap()
+    }
+
+    /// Actions that aren't in `json`, e.g. because they are newer than the saved bindings, get
+    /// their default key if it's still free. Actions saved without keys stay unbound.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let data: BTreeMap<Action, Vec<String>> =
+            serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+        let mut bindings = Self::empty();
+        for (action, names) in data.iter() {
+            for name in names.iter() {
+                let key = key_from_name(name).ok_or_else(|| format!("unknown key {}", name))?;
+                if let Some(other) = bindings.conflict(key, *action) {
+                    return Err(format!(
+                        "{} is bound to both {} and {}",
+                        name,
+                        other.label(),
+                        action.label()
+                    ));
+                }
+                bindings.bind(key, *action);
+            }
+        }
+
+        for action in Action::ALL.iter() {
+            let key = action.default_key();
+            if !data.contains_key(action) && bindings.action(key).is_none() {
+                bindings.bind(key, *action);
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    /// The saved bindings, or the defaults if nothing usable was saved.
+    pub fn load(platform: &dyn Platform) -> Self {
+        platform
+            .load_setting(SETTINGS_KEY)
+            .and_then(|json| Self::from_json(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, platform: &dyn Platform) {
+        platform.save_setting(SETTINGS_KEY, &self.to_json());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for (key, name) in KEY_NAMES.iter() {
+            assert_eq!(key_name(*key), Some(*name));
+            assert_eq!(key_from_name(name), Some(*key));
+        }
+        assert_eq!(key_name(VirtualKeyCode::Numpad5), None);
+        assert_eq!(key_from_name("w"), None);
+    }
+
+    #[test]
+    fn defaults_round_trip() {
+        let bindings = KeyBindings::default();
+        let loaded = KeyBindings::from_json(&bindings.to_json()).unwrap();
+        for action in Action::ALL.iter() {
+            assert_eq!(
+                loaded.keys(*action).collect::<Vec<_>>(),
+                vec![action.default_key()]
+            );
+        }
+    }
+
+    #[test]
+    fn rebound_keys_round_trip() {
+        let mut bindings = KeyBindings::default();
+        bindings
+            .rebind(Action::MoveForward, VirtualKeyCode::Up)
+            .unwrap();
+        bindings.bind(VirtualKeyCode::I, Action::MoveForward);
+        bindings.unbind(VirtualKeyCode::Space);
+
+        let json = bindings.to_json();
+        assert!(json.contains(r#""move_forward":["I","Up"]"#), "{}", json);
+
+        let loaded = KeyBindings::from_json(&json).unwrap();
+        let mut keys = loaded.keys(Action::MoveForward).collect::<Vec<_>>();
+        keys.sort_unstable_by_key(|key| key_name(*key));
+        assert_eq!(keys, vec![VirtualKeyCode::I, VirtualKeyCode::Up]);
+        assert_eq!(loaded.action(VirtualKeyCode::W), None);
+        // Unbinding sticks, Jump doesn't get its default back.
+        assert!(json.contains(r#""jump":[]"#), "{}", json);
+        assert_eq!(loaded.keys(Action::Jump).count(), 0);
+        assert_eq!(loaded.action(VirtualKeyCode::Space), None);
+    }
+
+    #[test]
+    fn missing_actions_get_free_default_keys() {
+        let loaded = KeyBindings::from_json(r#"{"chat": ["Space"]}"#).unwrap();
+        assert_eq!(loaded.action(VirtualKeyCode::Space), Some(Action::Chat));
+        assert_eq!(loaded.keys(Action::Jump).count(), 0);
+        assert_eq!(loaded.action(VirtualKeyCode::W), Some(Action::MoveForward));
+    }
+
+    #[test]
+    fn key_names_are_sorted() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(VirtualKeyCode::Up, Action::MoveForward);
+        bindings.bind(VirtualKeyCode::I, Action::MoveForward);
+        assert_eq!(
+            bindings.key_names(Action::MoveForward),
+            vec!["I", "Up", "W"]
+        );
+
+        bindings.unbind(VirtualKeyCode::Space);
+        assert!(bindings.key_names(Action::Jump).is_empty());
+    }
+
+    #[test]
+    fn unbound_actions_stay_unbound() {
+        let loaded = KeyBindings::from_json(r#"{"jump": []}"#).unwrap();
+        assert_eq!(loaded.keys(Action::Jump).count(), 0);
+        assert_eq!(loaded.action(VirtualKeyCode::Space), None);
+        assert_eq!(loaded.action(VirtualKeyCode::W), Some(Action::MoveForward));
+    }
+
+    #[test]
+    fn bad_json_is_rejected() {
+        assert!(KeyBindings::from_json("").is_err());
+        assert!(KeyBindings::from_json("[]").is_err());
+        assert!(KeyBindings::from_json(r#"{"jump": "Space"}"#).is_err());
+        assert!(KeyBindings::from_json(r#"{"teleport": ["T"]}"#).is_err());
+
+        let err = KeyBindings::from_json(r#"{"jump": ["Spacebar"]}"#).unwrap_err();
+        assert_eq!(err, "unknown key Spacebar");
+    }
+
+    #[test]
+    fn a_key_bound_twice_is_rejected() {
+        let err = KeyBindings::from_json(r#"{"jump": ["Space"], "chat": ["Space"]}"#).unwrap_err();
+        assert!(err.starts_with("Space is bound to both"), "{}", err);
+    }
+
+    #[test]
+    fn conflicting_rebind_is_rejected() {
+        let mut bindings = KeyBindings::default();
+        let before = bindings.to_json();
+
+        assert_eq!(
+            bindings.rebind(Action::Jump, VirtualKeyCode::W),
+            Err(Action::MoveForward)
+        );
+        assert_eq!(bindings.to_json(), before);
+
+        // Rebinding an action to its own key is fine.
+        assert_eq!(bindings.rebind(Action::Jump, VirtualKeyCode::Space), Ok(()));
+        assert_eq!(bindings.to_json(), before);
+    }
+
+    #[test]
+    fn rebind_replaces_the_old_keys() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(VirtualKeyCode::J, Action::Jump);
+        bindings
+            .rebind(Action::Jump, VirtualKeyCode::LControl)
+            .unwrap();
+
+        assert_eq!(
+            bindings.keys(Action::Jump).collect::<Vec<_>>(),
+            vec![VirtualKeyCode::LControl]
+        );
+        assert_eq!(bindings.action(VirtualKeyCode::Space), None);
+        assert_eq!(bindings.action(VirtualKeyCode::J), None);
+    }
+}
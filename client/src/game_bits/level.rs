@@ -0,0 +1,663 @@
+// Level files (.json under assets/levels) describe a level as data: primitive meshes, lights,
+// props, spawn points and where the camera starts. They are checked against the manifest before
+// anything gets built, and every problem is reported with the field it was found in.
+
+use rg3d::{
+    core::{
+        algebra::{Isometry3, Matrix4, UnitQuaternion, Vector3},
+        color::Color,
+        pool::Handle,
+    },
+    engine::resource_manager::ResourceManager,
+    physics::{dynamics::RigidBodyBuilder, geometry::ColliderBuilder},
+    resource::texture::Texture,
+    scene::{
+        base::BaseBuilder,
+        light::{BaseLightBuilder, DirectionalLightBuilder, PointLightBuilder, SpotLightBuilder},
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData},
+            MeshBuilder,
+        },
+        node::Node,
+        transform::TransformBuilder,
+        Scene,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use super::{
+    assets::{placeholder_texture, AssetError, AssetKind, AssetTracker, Manifest},
+    props::{load_prop_assets, spawn_prop, PropPlacement},
+    scene_builder::{create_camera_or_fallback, LoadedScene},
+    skybox::DEFAULT_SKYBOX,
+};
+
+/// The built-in test level.
+pub const DEFAULT_LEVEL: &str = "levels.test";
+
+fn default_scale() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn white() -> [u8; 3] {
+    [255; 3]
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeshShape {
+    /// Unit cube centered on the position, so the scale is its size.
+    Cube,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MeshDescription {
+    pub shape: MeshShape,
+    pub position: [f32; 3],
+    /// Euler angles in degrees.
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 3],
+    /// Texture id from the manifest, None for plain white.
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Whether the mesh gets a static collider of the same size.
+    #[serde(default)]
+    pub collider: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightKind {
+    Point,
+    /// Shines down its local -Y axis, rotate it to aim.
+    Spot,
+    Directional,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightDescription {
+    pub kind: LightKind,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "white")]
+    pub color: [u8; 3],
+    /// Point lights only.
+    #[serde(default)]
+    pub radius: Option<f32>,
+    /// Spot lights only.
+    #[serde(default)]
+    pub distance: Option<f32>,
+    /// Spot lights only, full cone angle in degrees.
+    #[serde(default)]
+    pub hotspot_angle: Option<f32>,
+    /// Spot lights only, extra angle in degrees over which the light fades out.
+    #[serde(default)]
+    pub falloff_angle: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnPoint {
+    pub position: [f32; 3],
+    /// Degrees around the Y axis, 0 looks down +Z.
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraStart {
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelDescription {
+    pub ambient_color: [u8; 3],
+    /// Skybox name from the manifest.
+    #[serde(default)]
+    pub skybox: Option<String>,
+    pub camera: CameraStart,
+    #[serde(default)]
+    pub meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub props: Vec<PropPlacement>,
+    pub spawn_points: Vec<SpawnPoint>,
+}
+
+/// A problem with one field of a level file.
+#[derive(Clone, Debug)]
+pub struct FieldError {
+    /// Path of the field, e.g. `meshes[2].scale`, or the position in the file for syntax errors.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+fn check_positive(errors: &mut Vec<FieldError>, field: String, values: &[f32]) {
+    if values.iter().any(|value| *value <= 0.0) {
+        errors.push(FieldError::new(field, "must be greater than zero"));
+    }
+}
+
+// For fields that are optional in the file, but needed by some kinds of objects.
+fn check_required(errors: &mut Vec<FieldError>, field: String, value: Option<f32>) {
+    match value {
+        Some(value) => check_positive(errors, field, &[value]),
+        None => errors.push(FieldError::new(field, "missing")),
+    }
+}
+
+fn check_texture(errors: &mut Vec<FieldError>, field: String, manifest: &Manifest, id: &str) {
+    match manifest.assets.get(id) {
+        Some(entry) if entry.kind == AssetKind::Texture => (),
+        Some(_) => errors.push(FieldError::new(field, format!("{} is not a texture", id))),
+        None => errors.push(FieldError::new(
+            field,
+            format!("no texture {} in the manifest", id),
+        )),
+    }
+}
+
+impl LevelDescription {
+    pub fn from_json(json: &str) -> Result<Self, FieldError> {
+        serde_json::from_str(json).map_err(|err| {
+            FieldError::new(
+                format!("line {}, column {}", err.line(), err.column()),
+                err.to_string(),
+            )
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Everything that is wrong with the level, empty if it can be built.
+    pub fn validate(&self, manifest: &Manifest) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if let Some(skybox) = self.skybox.as_ref() {
+            if !manifest.skyboxes.contains_key(skybox) {
+                errors.push(FieldError::new(
+                    "skybox",
+                    format!("no skybox {} in the manifest", skybox),
+                ));
+            }
+        }
+
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            check_positive(&mut errors, format!("meshes[{}].scale", i), &mesh.scale);
+            if let Some(texture) = mesh.texture.as_ref() {
+                check_texture(
+                    &mut errors,
+                    format!("meshes[{}].texture", i),
+                    manifest,
+                    texture,
+                );
+            }
+        }
+
+        for (i, light) in self.lights.iter().enumerate() {
+            let field = |name: &str| format!("lights[{}].{}", i, name);
+
+            match light.kind {
+                LightKind::Point => check_required(&mut errors, field("radius"), light.radius),
+                LightKind::Spot => {
+                    check_required(&mut errors, field("distance"), light.distance);
+                    check_required(&mut errors, field("hotspot_angle"), light.hotspot_angle);
+                    if light.hotspot_angle.map_or(false, |angle| angle >= 180.0) {
+                        errors.push(FieldError::new(
+                            field("hotspot_angle"),
+                            "must be less than 180 degrees",
+                        ));
+                    }
+                    if light.falloff_angle.map_or(false, |angle| angle < 0.0) {
+                        errors.push(FieldError::new(
+                            field("falloff_angle"),
+                            "must not be negative",
+                        ));
+                    }
+                }
+                LightKind::Directional => (),
+            }
+        }
+
+        for (i, prop) in self.props.iter().enumerate() {
+            check_positive(&mut errors, format!("props[{}].scale", i), &[prop.scale]);
+        }
+
+        if self.spawn_points.is_empty() {
+            errors.push(FieldError::new(
+                "spawn_points",
+                "needs at least one spawn point",
+            ));
+        }
+
+        errors
+    }
+}
+
+fn rotation_from_degrees([x, y, z]: [f32; 3]) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_euler_angles(x.to_radians(), y.to_radians(), z.to_radians())
+}
+
+fn color_from_rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::opaque(r, g, b)
+}
+
+/// Loads and validates the level file `level`, a manifest id or a path.
+pub async fn load_level_description(
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    level: &str,
+) -> Result<LevelDescription, AssetError> {
+    let url = manifest.resolve(level);
+    let data = tracker.file(&url).await?;
+
+    let invalid = |errors: Vec<FieldError>| {
+        tracker.record_failure(AssetError::Invalid {
+            path: url.clone(),
+            errors: errors.iter().map(|err| err.to_string()).collect(),
+        })
+    };
+
+    let json = String::from_utf8(data)
+        .map_err(|err| invalid(vec![FieldError::new("file", err.to_string())]))?;
+    let description = LevelDescription::from_json(&json).map_err(|err| invalid(vec![err]))?;
+
+    let errors = description.validate(manifest);
+    if !errors.is_empty() {
+        return Err(invalid(errors));
+    }
+
+    Ok(description)
+}
+
+/// Builds a scene from a validated level description. Missing textures and models get their
+/// placeholders.
+pub async fn build_level_scene(
+    resource_manager: ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    description: &LevelDescription,
+) -> LoadedScene {
+    let mut scene = Scene::new();
+    scene.ambient_lighting_color = color_from_rgb(description.ambient_color);
+
+    let skybox = description.skybox.as_deref().unwrap_or(DEFAULT_SKYBOX);
+    let camera = create_camera_or_fallback(
+        resource_manager.clone(),
+        tracker,
+        manifest,
+        skybox,
+        Vector3::from(description.camera.position),
+        &mut scene.graph,
+    )
+    .await;
+    scene.graph[camera]
+        .local_transform_mut()
+        .set_rotation(rotation_from_degrees(description.camera.rotation));
+
+    for mesh in description.meshes.iter() {
+        build_mesh(&resource_manager, tracker, manifest, &mut scene, mesh).await;
+    }
+
+    for light in description.lights.iter() {
+        build_light(&mut scene, light);
+    }
+
+    // Assets are loaded once per kind of prop, however many of them the level has.
+    let mut prop_assets = HashMap::new();
+    for placement in description.props.iter() {
+        if !prop_assets.contains_key(&placement.kind) {
+            let assets =
+                load_prop_assets(&resource_manager, tracker, manifest, placement.kind).await;
+            prop_assets.insert(placement.kind, assets);
+        }
+        spawn_prop(&mut scene, &prop_assets[&placement.kind], placement);
+    }
+
+    LoadedScene {
+        scene,
+        spawn_points: description.spawn_points.clone(),
+    }
+}
+
+async fn build_mesh(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    scene: &mut Scene,
+    mesh: &MeshDescription,
+) -> Handle<Node> {
+    let texture = match mesh.texture.as_ref() {
+        // Validation made sure the id is in the manifest.
+        Some(id) => Some(
+            tracker
+                .texture(resource_manager, &manifest.resolve(id))
+                .await
+                .unwrap_or_else(|_| placeholder_texture()),
+        ),
+        None => None,
+    };
+
+    spawn_mesh(scene, mesh, texture)
+}
+
+/// Adds `mesh` to the scene, with its collider bound to it so picking finds the node.
+pub fn spawn_mesh(
+    scene: &mut Scene,
+    mesh: &MeshDescription,
+    texture: Option<Texture>,
+) -> Handle<Node> {
+    let position = Vector3::from(mesh.position);
+    let rotation = rotation_from_degrees(mesh.rotation);
+    let scale = Vector3::from(mesh.scale);
+
+    let surface_data = match mesh.shape {
+        MeshShape::Cube => SurfaceData::make_cube(Matrix4::new_nonuniform_scaling(&scale)),
+    };
+    let mut surface = SurfaceBuilder::new(Arc::new(RwLock::new(surface_data)));
+    if let Some(texture) = texture {
+        surface = surface.with_diffuse_texture(texture);
+    }
+
+    let handle = MeshBuilder::new(
+        BaseBuilder::new().with_local_transform(
+            TransformBuilder::new()
+                .with_local_position(position)
+                .with_local_rotation(rotation)
+                .build(),
+        ),
+    )
+    .with_surfaces(vec![surface.build()])
+    .build(&mut scene.graph);
+
+    if mesh.collider {
+        let body = scene.physics.add_body(
+            RigidBodyBuilder::new_static()
+                .position(Isometry3::from_parts(position.into(), rotation))
+                .build(),
+        );
+        let half_extents = scale * 0.5;
+        scene.physics.add_collider(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z).build(),
+            &body,
+        );
+        scene.physics_binder.bind(handle, body);
+    }
+
+    handle
+}
+
+fn build_light(scene: &mut Scene, light: &LightDescription) -> Handle<Node> {
+    let base_light = BaseLightBuilder::new(
+        BaseBuilder::new().with_local_transform(
+            TransformBuilder::new()
+                .with_local_position(Vector3::from(light.position))
+                .with_local_rotation(rotation_from_degrees(light.rotation))
+                .build(),
+        ),
+    )
+    .with_color(color_from_rgb(light.color));
+
+    // Validation made sure the kind's own fields are there.
+    match light.kind {
+        LightKind::Point => PointLightBuilder::new(base_light)
+            .with_radius(light.radius.unwrap_or_default())
+            .build(&mut scene.graph),
+        LightKind::Spot => SpotLightBuilder::new(base_light)
+            .with_distance(light.distance.unwrap_or_default())
+            .with_hotspot_cone_angle(light.hotspot_angle.unwrap_or_default().to_radians())
+            .with_falloff_angle_delta(light.falloff_angle.unwrap_or_default().to_radians())
+            .build(&mut scene.graph),
+        LightKind::Directional => DirectionalLightBuilder::new(base_light).build(&mut scene.graph),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "assets": {
+            "textures.floor": { "kind": "texture", "path": "assets/textures/floor.jpg" },
+            "textures.sky": { "kind": "texture", "path": "assets/textures/sky.jpg" },
+            "models.barrel": { "kind": "model", "path": "assets/models/barrel.FBX" }
+        },
+        "skyboxes": {
+            "plain": {
+                "front": "textures.sky", "back": "textures.sky", "left": "textures.sky",
+                "right": "textures.sky", "top": "textures.sky", "bottom": "textures.sky"
+            }
+        }
+    }"#;
+
+    const LEVEL: &str = r#"{
+        "ambient_color": [200, 200, 200],
+        "skybox": "plain",
+        "camera": { "position": [0.0, 6.0, -12.0] },
+        "meshes": [
+            { "shape": "cube", "position": [0.0, -0.25, 0.0], "scale": [25.0, 0.25, 25.0],
+              "texture": "textures.floor", "collider": true }
+        ],
+        "lights": [
+            { "kind": "point", "position": [0.0, 12.0, 0.0], "radius": 20.0 },
+            { "kind": "spot", "distance": 10.0, "hotspot_angle": 45.0, "falloff_angle": 10.0 },
+            { "kind": "directional", "rotation": [-45.0, 0.0, 0.0] }
+        ],
+        "props": [
+            { "kind": "barrel", "position": [3.0, 0.4, 2.0], "physics": "static" }
+        ],
+        "spawn_points": [{ "position": [0.0, 1.0, 0.0], "yaw": 90.0 }]
+    }"#;
+
+    fn valid_level() -> LevelDescription {
+        LevelDescription::from_json(LEVEL).unwrap()
+    }
+
+    // Field and message of every error.
+    fn errors(level: &LevelDescription) -> Vec<(String, String)> {
+        let manifest = Manifest::from_json(MANIFEST).unwrap();
+        level
+            .validate(&manifest)
+            .into_iter()
+            .map(|err| (err.field, err.message))
+            .collect()
+    }
+
+    fn error(field: &str, message: &str) -> Vec<(String, String)> {
+        vec![(field.to_string(), message.to_string())]
+    }
+
+    #[test]
+    fn valid_level_has_no_errors() {
+        let level = valid_level();
+        assert_eq!(level.meshes.len(), 1);
+        assert_eq!(level.lights[1].kind, LightKind::Spot);
+        assert_eq!(level.meshes[0].rotation, [0.0; 3]);
+        assert_eq!(level.spawn_points[0].yaw, 90.0);
+        assert!(errors(&level).is_empty());
+    }
+
+    #[test]
+    fn defaults_fill_in_optional_fields() {
+        let level = LevelDescription::from_json(
+            r#"{
+                "ambient_color": [0, 0, 0],
+                "camera": { "position": [0.0, 0.0, 0.0] },
+                "meshes": [{ "shape": "cube", "position": [0.0, 0.0, 0.0] }],
+                "spawn_points": [{ "position": [0.0, 0.0, 0.0] }]
+            }"#,
+        )
+        .unwrap();
+        assert!(level.skybox.is_none());
+        assert_eq!(level.meshes[0].scale, [1.0; 3]);
+        assert!(!level.meshes[0].collider);
+        assert!(level.lights.is_empty());
+        assert!(errors(&level).is_empty());
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let json = valid_level().to_json();
+        assert!(errors(&LevelDescription::from_json(&json).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn unknown_skybox() {
+        let mut level = valid_level();
+        level.skybox = Some("sunny".to_string());
+        assert_eq!(
+            errors(&level),
+            error("skybox", "no skybox sunny in the manifest")
+        );
+    }
+
+    #[test]
+    fn mesh_scale_must_be_positive() {
+        let mut level = valid_level();
+        level.meshes[0].scale = [1.0, 0.0, 1.0];
+        assert_eq!(
+            errors(&level),
+            error("meshes[0].scale", "must be greater than zero")
+        );
+    }
+
+    #[test]
+    fn mesh_texture_must_be_a_texture_in_the_manifest() {
+        let mut level = valid_level();
+        level.meshes[0].texture = Some("textures.wall".to_string());
+        assert_eq!(
+            errors(&level),
+            error(
+                "meshes[0].texture",
+                "no texture textures.wall in the manifest"
+            )
+        );
+
+        level.meshes[0].texture = Some("models.barrel".to_string());
+        assert_eq!(
+            errors(&level),
+            error("meshes[0].texture", "models.barrel is not a texture")
+        );
+    }
+
+    #[test]
+    fn point_lights_need_a_radius() {
+        let mut level = valid_level();
+        level.lights[0].radius = None;
+        assert_eq!(errors(&level), error("lights[0].radius", "missing"));
+
+        level.lights[0].radius = Some(-1.0);
+        assert_eq!(
+            errors(&level),
+            error("lights[0].radius", "must be greater than zero")
+        );
+    }
+
+    #[test]
+    fn spot_lights_need_a_distance_and_angles() {
+        let mut level = valid_level();
+        level.lights[1].distance = None;
+        assert_eq!(errors(&level), error("lights[1].distance", "missing"));
+
+        let mut level = valid_level();
+        level.lights[1].hotspot_angle = None;
+        assert_eq!(errors(&level), error("lights[1].hotspot_angle", "missing"));
+
+        level.lights[1].hotspot_angle = Some(180.0);
+        assert_eq!(
+            errors(&level),
+            error("lights[1].hotspot_angle", "must be less than 180 degrees")
+        );
+
+        let mut level = valid_level();
+        level.lights[1].falloff_angle = Some(-5.0);
+        assert_eq!(
+            errors(&level),
+            error("lights[1].falloff_angle", "must not be negative")
+        );
+    }
+
+    #[test]
+    fn prop_scale_must_be_positive() {
+        let mut level = valid_level();
+        level.props[0].scale = 0.0;
+        assert_eq!(
+            errors(&level),
+            error("props[0].scale", "must be greater than zero")
+        );
+    }
+
+    #[test]
+    fn levels_need_a_spawn_point() {
+        let mut level = valid_level();
+        level.spawn_points.clear();
+        assert_eq!(
+            errors(&level),
+            error("spawn_points", "needs at least one spawn point")
+        );
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        let mut level = valid_level();
+        level.skybox = Some("sunny".to_string());
+        level.lights[0].radius = None;
+        level.spawn_points.clear();
+        let fields = errors(&level)
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect::<Vec<String>>();
+        assert_eq!(fields, vec!["skybox", "lights[0].radius", "spawn_points"]);
+    }
+
+    #[test]
+    fn malformed_json_reports_where() {
+        let err = LevelDescription::from_json("{\n  \"ambient_color\": [0, 0, 0],\n  oops\n}")
+            .unwrap_err();
+        assert_eq!(err.field, "line 3, column 3");
+        assert!(err.message.starts_with("key must be a string"));
+
+        // Typos in field names are caught too.
+        let json = LEVEL.replace("\"yaw\"", "\"yaww\"");
+        let err = LevelDescription::from_json(&json).unwrap_err();
+        assert!(err.message.starts_with("unknown field `yaww`"));
+
+        let err = LevelDescription::from_json(r#"{ "ambient_color": [0, 0, 0] }"#).unwrap_err();
+        assert!(err.message.starts_with("missing field `camera`"));
+    }
+}
@@ -0,0 +1,111 @@
+// Loads levels, either level files (see level.rs) or scenes saved with the rg3d editor (.rgs
+// files under assets/models). Some editor scenes come with their own camera and some don't, in
+// which case one is created with the usual skybox.
+
+use rg3d::{
+    core::{algebra::Vector3, color::Color, pool::Handle},
+    engine::resource_manager::ResourceManager,
+    scene::{node::Node, Scene},
+};
+
+use std::sync::Arc;
+
+use super::{
+    assets::{AssetError, AssetTracker, Manifest},
+    level::{build_level_scene, load_level_description},
+    scene_builder::{create_camera_or_fallback, LoadedScene},
+    skybox::DEFAULT_SKYBOX,
+};
+
+// Where the camera goes when the level doesn't have one.
+const DEFAULT_CAMERA_POSITION: [f32; 3] = [0.0, 6.0, -12.0];
+
+pub struct LoadedLevel {
+    /// Root of the instantiated level in the scene graph.
+    pub root: Handle<Node>,
+    pub camera: Handle<Node>,
+    /// True if the level had no camera and the default one was added.
+    pub created_camera: bool,
+}
+
+/// Instantiates `level` into `scene`, making sure the scene ends up with a camera. The level is
+/// either a manifest id or a path.
+pub async fn load_level(
+    resource_manager: ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    level: &str,
+    scene: &mut Scene,
+) -> Result<LoadedLevel, AssetError> {
+    let model = tracker
+        .model(&resource_manager, &manifest.resolve(level))
+        .await?;
+
+    // Geometry only, together with whatever rigid bodies and colliders the level has.
+    let root = model.instantiate_geometry(scene);
+
+    let existing_camera = scene
+        .graph
+        .traverse_handle_iter(root)
+        .find(|&handle| matches!(scene.graph[handle], Node::Camera(_)));
+
+    let (camera, created_camera) = match existing_camera {
+        Some(camera) => (camera, false),
+        None => {
+            let position = Vector3::from(DEFAULT_CAMERA_POSITION);
+            let camera = create_camera_or_fallback(
+                resource_manager,
+                tracker,
+                manifest,
+                DEFAULT_SKYBOX,
+                position,
+                &mut scene.graph,
+            )
+            .await;
+            (camera, true)
+        }
+    };
+
+    Ok(LoadedLevel {
+        root,
+        camera,
+        created_camera,
+    })
+}
+
+// Level files are .json, anything else is taken for an editor scene.
+fn is_level_file(manifest: &Manifest, level: &str) -> bool {
+    let path = manifest
+        .assets
+        .get(level)
+        .map_or(level, |entry| entry.path.as_str());
+    path.ends_with(".json")
+}
+
+/// Builds a scene around `level`, either a level file or an editor scene, given as manifest id
+/// or path. Without the level there is nothing to play, so unlike textures a missing level is an
+/// error.
+pub async fn load_level_scene(
+    resource_manager: ResourceManager,
+    tracker: AssetTracker,
+    manifest: Arc<Manifest>,
+    level: String,
+) -> Result<LoadedScene, AssetError> {
+    if is_level_file(&manifest, &level) {
+        let description = load_level_description(&tracker, &manifest, &level).await?;
+        return Ok(build_level_scene(resource_manager, &tracker, &manifest, &description).await);
+    }
+
+    let mut scene = Scene::new();
+
+    // Ambient lighting belongs to the scene, not to the level model, so it isn't part of the
+    // instance.
+    scene.ambient_lighting_color = Color::opaque(200, 200, 200);
+
+    load_level(resource_manager, &tracker, &manifest, &level, &mut scene).await?;
+
+    Ok(LoadedScene {
+        scene,
+        spawn_points: Vec::new(),
+    })
+}
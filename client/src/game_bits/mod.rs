@@ -1,3 +1,31 @@
+pub mod app;
+pub mod assets;
+pub mod camera;
+pub mod clock;
+pub mod fade;
 pub mod game_scene;
+pub mod gamepad;
+pub mod hot_reload;
+pub mod input;
+pub mod key_bindings;
+pub mod level;
+pub mod level_loader;
+pub mod net;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native_websocket;
+pub mod picking;
+pub mod platform;
+pub mod pointer_lock;
+pub mod props;
+pub mod protocol;
+pub mod recording;
+pub mod scene_builder;
+pub mod scene_manager;
+pub mod skybox;
+pub mod states;
+pub mod step;
+pub mod touch;
+#[cfg(target_arch = "wasm32")]
 pub mod websocket;
-pub mod js_channel;
\ No newline at end of file
+#[cfg(target_arch = "wasm32")]
+pub mod js_channel;
@@ -0,0 +1,146 @@
+// Websocket transport for the desktop build. The socket lives on its own thread so the game loop
+// never blocks on the network, messages are passed back and forth through channels.
+
+use std::{
+    io,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tungstenite::{http::Request, Message, WebSocket};
+
+use super::{
+    net::{ConnectionStatus, Transport},
+    protocol::{self, IncomingMessage, OutgoingMessage},
+};
+
+// How long the socket thread waits for incoming data before checking for outgoing messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub struct NativeWebsocket {
+    outgoing: Sender<OutgoingMessage>,
+    client_id: Arc<AtomicU32>,
+    status: Arc<Mutex<ConnectionStatus>>,
+}
+
+impl NativeWebsocket {
+    /// `origin` goes into the Origin header, the server only accepts sockets from the game's
+    /// page.
+    pub fn new(url: &str, origin: &str) -> NativeWebsocket {
+        let (outgoing, outgoing_rx) = mpsc::channel();
+        let client_id = Arc::new(AtomicU32::new(0));
+        let status = Arc::new(Mutex::new(ConnectionStatus::Connecting));
+
+        let url = url.to_string();
+        let origin = origin.to_string();
+        let thread_client_id = client_id.clone();
+        let thread_status = status.clone();
+        thread::spawn(move || {
+            let reason = match run_socket(
+                &url,
+                &origin,
+                outgoing_rx,
+                &thread_client_id,
+                &thread_status,
+            ) {
+                Ok(()) => "server closed the connection".to_string(),
+                Err(err) => {
+                    eprintln!("websocket error: {}", err);
+                    err.to_string()
+                }
+            };
+            *thread_status.lock().unwrap() = ConnectionStatus::Closed(reason);
+        });
+
+        NativeWebsocket {
+            outgoing,
+            client_id,
+            status,
+        }
+    }
+}
+
+impl Transport for NativeWebsocket {
+    fn send(&self, message: &OutgoingMessage) {
+        // The socket thread is gone once the connection dropped, nothing to do but drop messages.
+        let _ = self.outgoing.send(message.clone());
+    }
+
+    fn client_id(&self) -> u32 {
+        self.client_id.load(Ordering::Relaxed)
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+fn run_socket(
+    url: &str,
+    origin: &str,
+    outgoing: Receiver<OutgoingMessage>,
+    client_id: &AtomicU32,
+    status: &Mutex<ConnectionStatus>,
+) -> tungstenite::Result<()> {
+    let request = Request::builder()
+        .uri(url)
+        .header("Origin", origin)
+        .body(())?;
+    let (mut ws, _) = tungstenite::connect(request)?;
+    ws.get_mut().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    ws.write_message(Message::Text(OutgoingMessage::Salutations.to_json()))?;
+
+    loop {
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => ws.write_message(Message::Text(message.to_json()))?,
+                Err(TryRecvError::Empty) => break,
+                // The game is gone, say goodbye.
+                Err(TryRecvError::Disconnected) => return ws.close(None),
+            }
+        }
+
+        match ws.read_message() {
+            Ok(Message::Text(text)) => handle_text(&mut ws, &text, client_id, status)?,
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(err))
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn handle_text(
+    ws: &mut WebSocket<TcpStream>,
+    text: &str,
+    client_id: &AtomicU32,
+    status: &Mutex<ConnectionStatus>,
+) -> tungstenite::Result<()> {
+    let payload = match IncomingMessage::from_json(text) {
+        Ok(payload) => payload,
+        Err(err) => {
+            eprintln!("bad message from server: {} ({})", text, err);
+            return Ok(());
+        }
+    };
+
+    if payload.is_welcome() {
+        client_id.store(payload.client_id, Ordering::Relaxed);
+        *status.lock().unwrap() = ConnectionStatus::Connected;
+    }
+
+    if let Some(reply) = protocol::reply_to(&payload) {
+        ws.write_message(Message::Text(reply.to_json()))?;
+    }
+
+    Ok(())
+}
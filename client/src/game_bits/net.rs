@@ -0,0 +1,22 @@
+use super::protocol::OutgoingMessage;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    /// Socket is opening or waiting for the server's welcome.
+    Connecting,
+    /// Welcome received, `client_id` is valid.
+    Connected,
+    /// Connection is gone, with a human readable reason.
+    Closed(String),
+}
+
+/// Connection to the game server, implemented by each platform (see `websocket` for the browser).
+/// Dropping it closes the connection.
+pub trait Transport {
+    fn send(&self, message: &OutgoingMessage);
+
+    /// Id the server gave us in its welcome message.
+    fn client_id(&self) -> u32;
+
+    fn status(&self) -> ConnectionStatus;
+}
@@ -0,0 +1,201 @@
+// Finding what is under the cursor, or in the middle of the screen while the pointer is locked.
+// A ray goes from the camera through that point and the first physics collider it hits is the
+// pick, so only things with a rigid body can be picked: props and level geometry with colliders.
+
+use rg3d::{
+    core::{
+        algebra::{Vector2, Vector3},
+        color::Color,
+        pool::Handle,
+    },
+    physics::{dynamics::RigidBodyHandle, geometry::InteractionGroups},
+    scene::{
+        node::Node,
+        physics::{Intersection, RayCastOptions},
+        Scene,
+    },
+};
+
+use super::app::{GameContext, GameEngine};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Pick {
+    /// The node the hit collider's body moves.
+    pub node: Handle<Node>,
+    pub body: RigidBodyHandle,
+    /// Where the ray hit, in world space.
+    pub point: Vector3<f32>,
+    /// Meters from the camera.
+    pub distance: f32,
+}
+
+/// Where to pick on the screen, in pixels: the middle while looking around with the pointer
+/// locked, otherwise the cursor. None while the cursor is outside the window.
+pub fn pick_position(ctx: &GameContext) -> Option<Vector2<f32>> {
+    if ctx.pointer_lock.is_locked() {
+        Some(Vector2::new(
+            ctx.screen_size.width as f32 * 0.5,
+            ctx.screen_size.height as f32 * 0.5,
+        ))
+    } else {
+        ctx.input.cursor_position().map(|(x, y)| Vector2::new(x, y))
+    }
+}
+
+/// The first thing `camera` sees at `screen_position`, a pixel position in a screen of
+/// `screen_size` pixels.
+pub fn pick(
+    scene: &Scene,
+    camera: Handle<Node>,
+    screen_position: Vector2<f32>,
+    screen_size: Vector2<f32>,
+) -> Option<Pick> {
+    let ray = match &scene.graph[camera] {
+        Node::Camera(camera) => camera.make_ray(screen_position, screen_size),
+        _ => return None,
+    };
+
+    // The ray goes all the way to the far clipping plane.
+    let max_len = ray.dir.norm();
+    let mut intersections: Vec<Intersection> = Vec::new();
+    scene.physics.cast_ray(
+        RayCastOptions {
+            ray,
+            max_len,
+            groups: InteractionGroups::all(),
+            sort_results: true,
+        },
+        &mut intersections,
+    );
+    let hit = intersections.first()?;
+    let body = scene.physics.colliders.get(hit.collider)?.parent();
+
+    // Bodies only know their node through the binder.
+    let node = scene
+        .graph
+        .traverse_handle_iter(scene.graph.get_root())
+        .find(|&node| scene.physics_binder.body_of(node) == Some(&body))?;
+
+    Some(Pick {
+        node,
+        body,
+        point: hit.position.coords,
+        distance: hit.toi,
+    })
+}
+
+/// Whether pushing the pick moves it, only dynamic bodies do.
+pub fn is_movable(scene: &Scene, pick: &Pick) -> bool {
+    scene
+        .physics
+        .bodies
+        .get(pick.body)
+        .map_or(false, |body| body.is_dynamic())
+}
+
+/// Knocks the pick along `impulse`, in newton seconds.
+pub fn push(scene: &mut Scene, pick: &Pick, impulse: Vector3<f32>) {
+    if let Some(body) = scene.physics.bodies.get_mut(pick.body) {
+        body.apply_impulse(impulse, true);
+    }
+}
+
+/// Tints one node and everything below it, putting back the colors of the last one.
+#[derive(Default)]
+pub struct Highlight {
+    scene: Handle<Scene>,
+    node: Handle<Node>,
+    // Meshes that were tinted with the colors of their surfaces before.
+    original_colors: Vec<(Handle<Node>, Vec<Color>)>,
+}
+
+impl Highlight {
+    /// Highlights `node` of `scene`, or nothing for `Handle::NONE`.
+    pub fn set(&mut self, engine: &mut GameEngine, scene: Handle<Scene>, node: Handle<Node>) {
+        if scene == self.scene && node == self.node {
+            return;
+        }
+
+        // After a level change the old highlight went away with its scene.
+        if scene == self.scene {
+            let graph = &mut engine.scenes[scene].graph;
+            for (mesh, colors) in self.original_colors.drain(..) {
+                if let Node::Mesh(mesh) = &mut graph[mesh] {
+                    for (surface, color) in mesh.surfaces_mut().iter_mut().zip(colors) {
+                        surface.set_color(color);
+                    }
+                }
+            }
+        }
+        self.original_colors.clear();
+
+        self.scene = scene;
+        self.node = node;
+        if node.is_none() {
+            return;
+        }
+
+        let graph = &mut engine.scenes[scene].graph;
+        let meshes = graph
+            .traverse_handle_iter(node)
+            .collect::<Vec<Handle<Node>>>();
+        for handle in meshes {
+            if let Node::Mesh(mesh) = &mut graph[handle] {
+                let mut colors = Vec::new();
+                for surface in mesh.surfaces_mut() {
+                    colors.push(surface.color());
+                    surface.set_color(Color::opaque(255, 210, 90));
+                }
+                self.original_colors.push((handle, colors));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_bits::{
+        app::TIMESTEP,
+        level::{spawn_mesh, MeshDescription, MeshShape},
+    };
+    use rg3d::scene::{base::BaseBuilder, camera::CameraBuilder};
+
+    fn cube(position: [f32; 3], collider: bool) -> MeshDescription {
+        MeshDescription {
+            shape: MeshShape::Cube,
+            position,
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+            texture: None,
+            collider,
+        }
+    }
+
+    // Picks the middle of the screen, the camera sits at the origin looking down +Z.
+    fn pick_middle(scene: &mut Scene) -> Option<Pick> {
+        let camera = CameraBuilder::new(BaseBuilder::new()).build(&mut scene.graph);
+        let screen_size = Vector2::new(800.0, 600.0);
+        scene.update(screen_size, TIMESTEP);
+        pick(scene, camera, screen_size * 0.5, screen_size)
+    }
+
+    #[test]
+    fn picks_level_meshes() {
+        let mut scene = Scene::new();
+        let wall = spawn_mesh(&mut scene, &cube([0.0, 0.0, 5.0], true), None);
+
+        let pick = pick_middle(&mut scene).unwrap();
+        assert_eq!(pick.node, wall);
+        assert!((pick.point.z - 4.5).abs() < 1e-3);
+        assert!(!is_movable(&scene, &pick));
+    }
+
+    #[test]
+    fn level_meshes_without_colliders_are_not_picked() {
+        let mut scene = Scene::new();
+        spawn_mesh(&mut scene, &cube([0.0, 0.0, 5.0], false), None);
+
+        assert!(pick_middle(&mut scene).is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use rg3d::window::Window;
+
+use std::{future::Future, pin::Pin};
+
+use super::{gamepad::GamepadState, net::Transport, touch::TouchEvent};
+
+pub type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Things the game needs that each front end provides differently.
+pub trait Platform {
+    /// Runs a future to completion in the background, e.g. scene loading.
+    fn spawn(&self, future: BoxedFuture);
+
+    /// Starts connecting to the game server, see `Transport::status` for progress.
+    fn connect(&self, url: &str) -> Box<dyn Transport>;
+
+    /// `url` made unique, so a browser can't answer it from its cache. Files on disk are always
+    /// fresh.
+    fn uncached_url(&self, url: &str) -> String {
+        url.to_string()
+    }
+
+    /// A setting saved by an earlier run, e.g. the key bindings.
+    fn load_setting(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    /// Keeps `value` around for the next run. Saving is best effort, a failure only means the
+    /// next run starts with the defaults.
+    fn save_setting(&self, _key: &str, _value: &str) {}
+
+    /// Connected gamepads with the standard button layout, polled once per time step.
+    fn gamepads(&self) -> Vec<GamepadState> {
+        Vec::new()
+    }
+
+    /// Touches since the last call, for platforms whose touches don't come as window events.
+    fn touch_events(&self) -> Vec<TouchEvent> {
+        Vec::new()
+    }
+
+    /// Captures the mouse and hides the cursor for looking around, or lets go of it. May be
+    /// granted later or not at all, see `is_pointer_locked`.
+    fn set_pointer_lock(&self, _window: &Window, _locked: bool) {}
+
+    /// Called when the window gains or loses focus, platforms that grab the pointer right away
+    /// shouldn't do it for a window in the background.
+    fn set_focused(&self, _focused: bool) {}
+
+    /// Platforms that can't lock the pointer always count as locked, so nothing waits for it.
+    fn is_pointer_locked(&self) -> bool {
+        true
+    }
+
+    fn set_fullscreen(&self, _window: &Window, _fullscreen: bool) {}
+
+    fn is_fullscreen(&self, _window: &Window) -> bool {
+        false
+    }
+
+    /// Hands an input recording to whoever asked for it, see recording.rs.
+    fn save_recording(&self, _json: &str) {}
+}
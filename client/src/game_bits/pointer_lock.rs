@@ -0,0 +1,129 @@
+// Pointer lock and fullscreen. Looking around needs the mouse captured, which the platform grants
+// and takes back as it likes: browsers only lock the pointer during a click and let go whenever
+// Escape is pressed. So like gamepads the state is polled once per time step, and changes found
+// by comparing with the previous poll become events the game can pause on.
+
+use rg3d::window::Window;
+
+use super::platform::Platform;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerLockEvent {
+    Locked,
+    Unlocked,
+}
+
+#[derive(Default)]
+pub struct PointerLock {
+    locked: bool,
+    fullscreen: bool,
+    // Locks gained and lost in this time step.
+    events: Vec<PointerLockEvent>,
+}
+
+impl PointerLock {
+    /// Call at the start of each time step with what the platform says, or a replay.
+    pub fn update(&mut self, locked: bool, fullscreen: bool) {
+        if locked != self.locked {
+            self.events.push(if locked {
+                PointerLockEvent::Locked
+            } else {
+                PointerLockEvent::Unlocked
+            });
+            self.locked = locked;
+        }
+        self.fullscreen = fullscreen;
+    }
+
+    /// Call at the end of each time step.
+    pub fn end_step(&mut self) {
+        self.events.clear();
+    }
+
+    /// Locks gained and lost in this time step.
+    pub fn events(&self) -> &[PointerLockEvent] {
+        &self.events
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Asks for the pointer, watch for `PointerLockEvent::Locked` to know when it's granted. In
+    /// the browser that is on the next click at the earliest.
+    pub fn request_lock(&self, platform: &dyn Platform, window: &Window) {
+        platform.set_pointer_lock(window, true);
+    }
+
+    pub fn exit_lock(&self, platform: &dyn Platform, window: &Window) {
+        platform.set_pointer_lock(window, false);
+    }
+
+    /// Like `request_lock`, browsers may wait for a click.
+    pub fn request_fullscreen(&self, platform: &dyn Platform, window: &Window) {
+        platform.set_fullscreen(window, true);
+    }
+
+    pub fn exit_fullscreen(&self, platform: &dyn Platform, window: &Window) {
+        platform.set_fullscreen(window, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_become_events() {
+        let mut lock = PointerLock::default();
+        assert!(!lock.is_locked());
+
+        lock.update(true, false);
+        assert!(lock.is_locked());
+        assert_eq!(lock.events(), &[PointerLockEvent::Locked]);
+        lock.end_step();
+        assert!(lock.events().is_empty());
+
+        lock.update(false, false);
+        assert!(!lock.is_locked());
+        assert_eq!(lock.events(), &[PointerLockEvent::Unlocked]);
+    }
+
+    #[test]
+    fn unchanged_polls_are_quiet() {
+        let mut lock = PointerLock::default();
+        lock.update(false, false);
+        assert!(lock.events().is_empty());
+
+        lock.update(true, false);
+        lock.end_step();
+        lock.update(true, false);
+        assert!(lock.events().is_empty());
+        assert!(lock.is_locked());
+    }
+
+    #[test]
+    fn lost_and_regained_in_one_step() {
+        let mut lock = PointerLock::default();
+        lock.update(true, false);
+        lock.update(false, false);
+        assert_eq!(
+            lock.events(),
+            &[PointerLockEvent::Locked, PointerLockEvent::Unlocked]
+        );
+    }
+
+    #[test]
+    fn fullscreen_follows_the_poll() {
+        let mut lock = PointerLock::default();
+        lock.update(false, true);
+        assert!(lock.is_fullscreen());
+        assert!(lock.events().is_empty());
+        lock.update(false, false);
+        assert!(!lock.is_fullscreen());
+    }
+}
@@ -0,0 +1,214 @@
+// Props are models scattered around a level, like the barrel. Their assets are loaded once per
+// kind and then instantiated at each placement, optionally with a rigid body so they can be
+// knocked around or shot at.
+
+use rg3d::{
+    core::{
+        algebra::{Isometry3, Translation3, UnitQuaternion, Vector3},
+        pool::Handle,
+    },
+    engine::resource_manager::ResourceManager,
+    physics::{dynamics::RigidBodyBuilder, geometry::ColliderBuilder},
+    resource::{model::Model, texture::Texture},
+    scene::{node::Node, transform::TransformBuilder, Scene},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::assets::{placeholder_mesh, placeholder_texture, AssetTracker, Manifest};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropKind {
+    Barrel,
+}
+
+impl PropKind {
+    // Manifest ids of the model and its diffuse and normal textures.
+    fn asset_ids(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            PropKind::Barrel => ("models.barrel", "textures.barrel", "textures.barrel_normal"),
+        }
+    }
+
+    // Half height and radius of the cylinder collider at scale 1, measured from the model, which
+    // is centered on its origin.
+    fn collider_size(self) -> (f32, f32) {
+        match self {
+            PropKind::Barrel => (0.52, 0.35),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropPhysics {
+    /// Just geometry, nothing collides with it.
+    None,
+    /// Collides, but never moves.
+    Static,
+    /// Falls, rolls and gets pushed around.
+    Dynamic,
+}
+
+impl Default for PropPhysics {
+    fn default() -> Self {
+        PropPhysics::None
+    }
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// Where and how to put a prop in the level.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PropPlacement {
+    pub kind: PropKind,
+    pub position: [f32; 3],
+    /// Euler angles in degrees.
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub physics: PropPhysics,
+}
+
+/// Loaded assets of one prop kind. Anything that failed to load is None and gets replaced by a
+/// placeholder when spawning.
+pub struct PropAssets {
+    pub kind: PropKind,
+    model: Option<Model>,
+    diffuse: Option<Texture>,
+    normal: Option<Texture>,
+}
+
+async fn request_texture(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    id: &str,
+) -> Option<Texture> {
+    match manifest.url(id) {
+        Ok(url) => tracker.texture(resource_manager, &url).await.ok(),
+        Err(err) => {
+            tracker.record_failure(err);
+            None
+        }
+    }
+}
+
+async fn request_model(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    id: &str,
+) -> Option<Model> {
+    match manifest.url(id) {
+        Ok(url) => tracker.model(resource_manager, &url).await.ok(),
+        Err(err) => {
+            tracker.record_failure(err);
+            None
+        }
+    }
+}
+
+/// Loads the model and textures of `kind`, failures end up on the tracker's list.
+pub async fn load_prop_assets(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    kind: PropKind,
+) -> PropAssets {
+    let (model_id, diffuse_id, normal_id) = kind.asset_ids();
+
+    let (model, diffuse, normal) = rg3d::core::futures::join!(
+        request_model(resource_manager, tracker, manifest, model_id),
+        request_texture(resource_manager, tracker, manifest, diffuse_id),
+        request_texture(resource_manager, tracker, manifest, normal_id)
+    );
+
+    PropAssets {
+        kind,
+        model,
+        diffuse,
+        normal,
+    }
+}
+
+// Textures come from the manifest rather than from the model file, so they get the same cache
+// busting as everything else.
+fn apply_textures(scene: &mut Scene, root: Handle<Node>, assets: &PropAssets) {
+    let diffuse = assets.diffuse.clone().unwrap_or_else(placeholder_texture);
+    let meshes = scene
+        .graph
+        .traverse_handle_iter(root)
+        .collect::<Vec<Handle<Node>>>();
+    for handle in meshes {
+        if let Node::Mesh(mesh) = &mut scene.graph[handle] {
+            for surface in mesh.surfaces_mut() {
+                surface.set_diffuse_texture(Some(diffuse.clone()));
+                surface.set_normal_texture(assets.normal.clone());
+            }
+        }
+    }
+}
+
+/// Instantiates a prop at `placement` and returns the root of its instance.
+pub fn spawn_prop(
+    scene: &mut Scene,
+    assets: &PropAssets,
+    placement: &PropPlacement,
+) -> Handle<Node> {
+    let kind = assets.kind;
+    let position = Vector3::from(placement.position);
+    let [x, y, z] = placement.rotation;
+    let rotation =
+        UnitQuaternion::from_euler_angles(x.to_radians(), y.to_radians(), z.to_radians());
+    let scale = Vector3::new(placement.scale, placement.scale, placement.scale);
+
+    let root = match assets.model.as_ref() {
+        Some(model) => {
+            let root = model.instantiate_geometry(scene);
+            scene.graph[root]
+                .local_transform_mut()
+                .set_position(position)
+                .set_rotation(rotation)
+                .set_scale(scale);
+            apply_textures(scene, root, assets);
+            root
+        }
+        None => placeholder_mesh(
+            &mut scene.graph,
+            TransformBuilder::new()
+                .with_local_position(position)
+                .with_local_rotation(rotation)
+                .with_local_scale(scale)
+                .build(),
+        ),
+    };
+
+    let body = match placement.physics {
+        PropPhysics::None => return root,
+        PropPhysics::Static => RigidBodyBuilder::new_static(),
+        PropPhysics::Dynamic => RigidBodyBuilder::new_dynamic(),
+    }
+    .position(Isometry3::from_parts(
+        Translation3::from(position),
+        rotation,
+    ))
+    .build();
+    let body = scene.physics.add_body(body);
+
+    let (half_height, radius) = kind.collider_size();
+    let collider =
+        ColliderBuilder::cylinder(half_height * placement.scale, radius * placement.scale).build();
+    scene.physics.add_collider(collider, &body);
+
+    // From now on the body moves the prop.
+    scene.physics_binder.bind(root, body);
+
+    root
+}
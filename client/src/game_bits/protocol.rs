@@ -0,0 +1,154 @@
+// Message types shared by everything that talks to the game server. These mirror the structs
+// in server/src/main.go. The server decodes JSON field names case-insensitively, but it encodes
+// its replies with Go's exported (PascalCase) field names, hence the two different renames.
+
+use serde::{Serialize, Deserialize};
+
+/// Messages sent from a client to the server.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "messageType", rename_all = "camelCase")]
+pub enum OutgoingMessage {
+    /// First message after the socket opens, the server answers with a `welcome`.
+    Salutations,
+    /// Acknowledges the id handed out by `welcome`.
+    #[serde(rename_all = "camelCase")]
+    Ack {
+        client_id: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    CursorPosition {
+        client_id: u32,
+        x: f64,
+        y: f64,
+    },
+}
+
+/// Messages received from the server.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct IncomingMessage {
+    pub message_type: String,
+    #[serde(default)]
+    pub client_id: u32,
+}
+
+impl OutgoingMessage {
+    pub fn to_json(&self) -> String {
+        // Serializing a plain enum into a string can't fail.
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl IncomingMessage {
+    pub fn from_json(text: &str) -> Result<IncomingMessage, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn is_welcome(&self) -> bool {
+        self.message_type == "welcome"
+    }
+}
+
+/// What a client has to send back after receiving `message`, if anything.
+pub fn reply_to(message: &IncomingMessage) -> Option<OutgoingMessage> {
+    if message.is_welcome() {
+        Some(OutgoingMessage::Ack {
+            client_id: message.client_id,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outgoing_messages_use_the_server_field_names() {
+        assert_eq!(
+            OutgoingMessage::Salutations.to_json(),
+            r#"{"messageType":"salutations"}"#
+        );
+        assert_eq!(
+            OutgoingMessage::Ack { client_id: 7 }.to_json(),
+            r#"{"messageType":"ack","clientId":7}"#
+        );
+        assert_eq!(
+            OutgoingMessage::CursorPosition {
+                client_id: 7,
+                x: 1.5,
+                y: -2.0
+            }
+            .to_json(),
+            r#"{"messageType":"cursorPosition","clientId":7,"x":1.5,"y":-2.0}"#
+        );
+    }
+
+    #[test]
+    fn outgoing_messages_round_trip() {
+        let messages = [
+            OutgoingMessage::Salutations,
+            OutgoingMessage::Ack { client_id: 42 },
+            OutgoingMessage::CursorPosition {
+                client_id: u32::MAX,
+                x: 100.25,
+                y: -3.5,
+            },
+        ];
+        for message in messages.iter() {
+            let parsed: OutgoingMessage = serde_json::from_str(&message.to_json()).unwrap();
+            assert_eq!(&parsed, message);
+        }
+    }
+
+    #[test]
+    fn welcome_from_the_server() {
+        // What Go's encoding/json writes for WelcomeMessage.
+        let text = r#"{"MessageType":"welcome","ClientId":3}"#;
+        let message = IncomingMessage::from_json(text).unwrap();
+        assert!(message.is_welcome());
+        assert_eq!(message.client_id, 3);
+
+        let text = serde_json::to_string(&message).unwrap();
+        assert_eq!(IncomingMessage::from_json(&text).unwrap(), message);
+    }
+
+    #[test]
+    fn other_messages_from_the_server() {
+        let message = IncomingMessage::from_json(r#"{"MessageType":"cursorPosition"}"#).unwrap();
+        assert!(!message.is_welcome());
+        assert_eq!(message.client_id, 0);
+
+        assert!(IncomingMessage::from_json(r#"{"ClientId":3}"#).is_err());
+        assert!(IncomingMessage::from_json("not json").is_err());
+    }
+
+    fn incoming(message_type: &str, client_id: u32) -> IncomingMessage {
+        IncomingMessage {
+            message_type: message_type.to_string(),
+            client_id,
+        }
+    }
+
+    #[test]
+    fn welcome_is_acked_with_its_id() {
+        assert_eq!(
+            reply_to(&incoming("welcome", 3)),
+            Some(OutgoingMessage::Ack { client_id: 3 })
+        );
+        assert_eq!(
+            reply_to(&incoming("welcome", u32::MAX)),
+            Some(OutgoingMessage::Ack {
+                client_id: u32::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn only_welcome_gets_a_reply() {
+        for message_type in ["cursorPosition", "ack", "Welcome", ""].iter() {
+            assert_eq!(reply_to(&incoming(message_type, 3)), None);
+        }
+    }
+}
@@ -0,0 +1,605 @@
+// Input recording and playback, for bug reports and regression runs. Every input the game reacts
+// to is logged together with the tick, the number of fixed time steps run before it was handled.
+// Playing a log back feeds the same inputs in before the same ticks while live input is ignored,
+// so the game logic sees exactly the same session again. Whatever doesn't come from the player
+// isn't in the log: a replay only goes the same way if loading and the server do too. That goes
+// for the window size as well, resizes aren't recorded because the renderer has to follow the
+// real window. The UI, picking and the touch controls go by that size, so replay in a window of
+// the size the recording was made in.
+
+use rg3d::{
+    dpi::PhysicalPosition,
+    event::{
+        DeviceEvent, DeviceId, ElementState, Event, KeyboardInput, ModifiersState, MouseButton,
+        MouseScrollDelta, TouchPhase, WindowEvent,
+    },
+    window::WindowId,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamepad::GamepadState,
+    key_bindings::{key_from_name, key_name},
+    touch::TouchEvent,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+/// One input, in a form that survives being written to a file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    /// By name, keys that have none aren't recorded.
+    Key {
+        key: String,
+        pressed: bool,
+    },
+    Character {
+        character: char,
+    },
+    MouseButton {
+        button: RecordedButton,
+        pressed: bool,
+    },
+    /// Cursor position in the window, the UI needs it for clicks.
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorLeft,
+    /// Raw mouse motion for looking around.
+    MouseMotion {
+        dx: f64,
+        dy: f64,
+    },
+    /// In lines, or in pixels for touchpads.
+    MouseWheel {
+        x: f64,
+        y: f64,
+        pixels: bool,
+    },
+    Focused {
+        focused: bool,
+    },
+    Touch {
+        id: u64,
+        phase: RecordedPhase,
+        x: f32,
+        y: f32,
+    },
+    /// Pads as polled, only logged when they changed.
+    Gamepads {
+        pads: Vec<GamepadState>,
+    },
+    /// Pointer lock and fullscreen as polled, only logged when either changed.
+    PointerLock {
+        locked: bool,
+        fullscreen: bool,
+    },
+}
+
+fn is_pressed(state: ElementState) -> bool {
+    state == ElementState::Pressed
+}
+
+fn element_state(pressed: bool) -> ElementState {
+    if pressed {
+        ElementState::Pressed
+    } else {
+        ElementState::Released
+    }
+}
+
+impl RecordedEvent {
+    /// The input in `event`, None for everything else, e.g. resizes.
+    pub fn from_event(event: &Event<()>) -> Option<Self> {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput { input, .. } => Some(RecordedEvent::Key {
+                    key: key_name(input.virtual_keycode?)?.to_string(),
+                    pressed: is_pressed(input.state),
+                }),
+                WindowEvent::ReceivedCharacter(character) => Some(RecordedEvent::Character {
+                    character: *character,
+                }),
+                WindowEvent::MouseInput { state, button, .. } => Some(RecordedEvent::MouseButton {
+                    button: match button {
+                        MouseButton::Left => RecordedButton::Left,
+                        MouseButton::Right => RecordedButton::Right,
+                        MouseButton::Middle => RecordedButton::Middle,
+                        MouseButton::Other(button) => RecordedButton::Other(*button),
+                    },
+                    pressed: is_pressed(*state),
+                }),
+                WindowEvent::CursorMoved { position, .. } => Some(RecordedEvent::CursorMoved {
+                    x: position.x,
+                    y: position.y,
+                }),
+                WindowEvent::CursorLeft { .. } => Some(RecordedEvent::CursorLeft),
+                WindowEvent::MouseWheel { delta, .. } => Some(match delta {
+                    MouseScrollDelta::LineDelta(x, y) => RecordedEvent::MouseWheel {
+                        x: *x as f64,
+                        y: *y as f64,
+                        pixels: false,
+                    },
+                    MouseScrollDelta::PixelDelta(position) => RecordedEvent::MouseWheel {
+                        x: position.x,
+                        y: position.y,
+                        pixels: true,
+                    },
+                }),
+                WindowEvent::Focused(focused) => Some(RecordedEvent::Focused { focused: *focused }),
+                WindowEvent::Touch(touch) => Some(RecordedEvent::from_touch(TouchEvent {
+                    id: touch.id,
+                    phase: touch.phase,
+                    x: touch.location.x as f32,
+                    y: touch.location.y as f32,
+                })),
+                _ => None,
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => Some(RecordedEvent::MouseMotion {
+                dx: delta.0,
+                dy: delta.1,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn from_touch(touch: TouchEvent) -> Self {
+        RecordedEvent::Touch {
+            id: touch.id,
+            phase: match touch.phase {
+                TouchPhase::Started => RecordedPhase::Started,
+                TouchPhase::Moved => RecordedPhase::Moved,
+                TouchPhase::Ended => RecordedPhase::Ended,
+                TouchPhase::Cancelled => RecordedPhase::Cancelled,
+            },
+            x: touch.x,
+            y: touch.y,
+        }
+    }
+
+    pub fn to_touch(&self) -> Option<TouchEvent> {
+        match *self {
+            RecordedEvent::Touch { id, phase, x, y } => Some(TouchEvent {
+                id,
+                phase: match phase {
+                    RecordedPhase::Started => TouchPhase::Started,
+                    RecordedPhase::Moved => TouchPhase::Moved,
+                    RecordedPhase::Ended => TouchPhase::Ended,
+                    RecordedPhase::Cancelled => TouchPhase::Cancelled,
+                },
+                x,
+                y,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Back into an event for the event loop handlers. None for the inputs that don't come as
+    /// events: touches, gamepads, pointer lock and fullscreen.
+    // The modifiers fields are deprecated but still have to be filled in.
+    #[allow(deprecated)]
+    pub fn to_event(&self) -> Option<Event<'static, ()>> {
+        // Nothing looks at which window or device an event came from.
+        let window_id = unsafe { WindowId::dummy() };
+        let device_id = unsafe { DeviceId::dummy() };
+        let modifiers = ModifiersState::empty();
+
+        let event = match *self {
+            RecordedEvent::Key { ref key, pressed } => WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode: 0,
+                    state: element_state(pressed),
+                    virtual_keycode: key_from_name(key),
+                    modifiers,
+                },
+                is_synthetic: false,
+            },
+            RecordedEvent::Character { character } => WindowEvent::ReceivedCharacter(character),
+            RecordedEvent::MouseButton { button, pressed } => WindowEvent::MouseInput {
+                device_id,
+                state: element_state(pressed),
+                button: match button {
+                    RecordedButton::Left => MouseButton::Left,
+                    RecordedButton::Right => MouseButton::Right,
+                    RecordedButton::Middle => MouseButton::Middle,
+                    RecordedButton::Other(button) => MouseButton::Other(button),
+                },
+                modifiers,
+            },
+            RecordedEvent::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(x, y),
+                modifiers,
+            },
+            RecordedEvent::CursorLeft => WindowEvent::CursorLeft { device_id },
+            RecordedEvent::MouseMotion { dx, dy } => {
+                return Some(Event::DeviceEvent {
+                    device_id,
+                    event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                })
+            }
+            RecordedEvent::MouseWheel { x, y, pixels } => WindowEvent::MouseWheel {
+                device_id,
+                delta: if pixels {
+                    MouseScrollDelta::PixelDelta(PhysicalPosition::new(x, y))
+                } else {
+                    MouseScrollDelta::LineDelta(x as f32, y as f32)
+                },
+                phase: TouchPhase::Moved,
+                modifiers,
+            },
+            RecordedEvent::Focused { focused } => WindowEvent::Focused(focused),
+            RecordedEvent::Touch { .. }
+            | RecordedEvent::Gamepads { .. }
+            | RecordedEvent::PointerLock { .. } => return None,
+        };
+
+        Some(Event::WindowEvent { window_id, event })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoggedEvent {
+    pub tick: u64,
+    pub event: RecordedEvent,
+}
+
+/// What gets saved, the events in the order they were handled.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct InputLog {
+    pub events: Vec<LoggedEvent>,
+}
+
+impl InputLog {
+    pub fn to_json(&self) -> String {
+        // Plain data, serializing it can't fail.
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    log: InputLog,
+    // Last logged polls, None until the first one.
+    gamepads: Option<Vec<GamepadState>>,
+    pointer_lock: Option<(bool, bool)>,
+}
+
+impl Recorder {
+    pub fn record(&mut self, tick: u64, event: RecordedEvent) {
+        self.log.events.push(LoggedEvent { tick, event });
+    }
+
+    /// Call with every poll, only changes are logged.
+    pub fn record_gamepads(&mut self, tick: u64, pads: &[GamepadState]) {
+        if self.gamepads.as_deref() != Some(pads) {
+            self.gamepads = Some(pads.to_vec());
+            self.record(
+                tick,
+                RecordedEvent::Gamepads {
+                    pads: pads.to_vec(),
+                },
+            );
+        }
+    }
+
+    /// Call with every poll, only changes are logged.
+    pub fn record_pointer_lock(&mut self, tick: u64, locked: bool, fullscreen: bool) {
+        if self.pointer_lock != Some((locked, fullscreen)) {
+            self.pointer_lock = Some((locked, fullscreen));
+            self.record(tick, RecordedEvent::PointerLock { locked, fullscreen });
+        }
+    }
+
+    pub fn log(&self) -> &InputLog {
+        &self.log
+    }
+}
+
+pub struct Replay {
+    events: Vec<LoggedEvent>,
+    next: usize,
+    // Polled state as of the last event taken.
+    gamepads: Vec<GamepadState>,
+    pointer_locked: bool,
+    fullscreen: bool,
+}
+
+impl Replay {
+    pub fn new(log: InputLog) -> Self {
+        Self {
+            events: log.events,
+            next: 0,
+            gamepads: Vec::new(),
+            pointer_locked: false,
+            fullscreen: false,
+        }
+    }
+
+    /// The events to handle before step `tick`, in order. Gamepads, pointer lock and fullscreen
+    /// are kept instead, see `gamepads`, `is_pointer_locked` and `is_fullscreen`.
+    pub fn take_events(&mut self, tick: u64) -> Vec<RecordedEvent> {
+        let mut events = Vec::new();
+        while let Some(logged) = self
+            .events
+            .get(self.next)
+            .filter(|logged| logged.tick <= tick)
+        {
+            match &logged.event {
+                RecordedEvent::Gamepads { pads } => self.gamepads = pads.clone(),
+                RecordedEvent::PointerLock { locked, fullscreen } => {
+                    self.pointer_locked = *locked;
+                    self.fullscreen = *fullscreen;
+                }
+                event => events.push(event.clone()),
+            }
+            self.next += 1;
+        }
+        events
+    }
+
+    pub fn gamepads(&self) -> Vec<GamepadState> {
+        self.gamepads.clone()
+    }
+
+    pub fn is_pointer_locked(&self) -> bool {
+        self.pointer_locked
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad(a: f32) -> GamepadState {
+        GamepadState {
+            index: 0,
+            name: "pad".to_string(),
+            axes: vec![0.0; 4],
+            buttons: vec![a],
+        }
+    }
+
+    fn key(key: &str, pressed: bool) -> RecordedEvent {
+        RecordedEvent::Key {
+            key: key.to_string(),
+            pressed,
+        }
+    }
+
+    fn log(events: Vec<(u64, RecordedEvent)>) -> InputLog {
+        InputLog {
+            events: events
+                .into_iter()
+                .map(|(tick, event)| LoggedEvent { tick, event })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn log_round_trips() {
+        let log = log(vec![
+            (0, key("W", true)),
+            (0, RecordedEvent::Character { character: 'w' }),
+            (
+                1,
+                RecordedEvent::MouseButton {
+                    button: RecordedButton::Other(4),
+                    pressed: false,
+                },
+            ),
+            (2, RecordedEvent::CursorMoved { x: 1.5, y: 2.0 }),
+            (2, RecordedEvent::CursorLeft),
+            (3, RecordedEvent::MouseMotion { dx: -3.0, dy: 0.25 }),
+            (
+                3,
+                RecordedEvent::MouseWheel {
+                    x: 0.0,
+                    y: -1.0,
+                    pixels: false,
+                },
+            ),
+            (4, RecordedEvent::Focused { focused: false }),
+            (
+                5,
+                RecordedEvent::Touch {
+                    id: 7,
+                    phase: RecordedPhase::Cancelled,
+                    x: 10.0,
+                    y: 20.0,
+                },
+            ),
+            (
+                6,
+                RecordedEvent::Gamepads {
+                    pads: vec![pad(1.0)],
+                },
+            ),
+            (
+                7,
+                RecordedEvent::PointerLock {
+                    locked: true,
+                    fullscreen: true,
+                },
+            ),
+        ]);
+
+        let loaded = InputLog::from_json(&log.to_json()).unwrap();
+        assert_eq!(loaded.events.len(), log.events.len());
+        for (loaded, logged) in loaded.events.iter().zip(log.events.iter()) {
+            assert_eq!(loaded.tick, logged.tick);
+            assert_eq!(loaded.event, logged.event);
+        }
+
+        assert!(InputLog::from_json(r#"{"events": [{"tick": 0}]}"#).is_err());
+    }
+
+    #[test]
+    fn events_survive_the_trip_through_winit() {
+        let events = vec![
+            key("Space", false),
+            RecordedEvent::CursorMoved { x: 3.0, y: 4.0 },
+            RecordedEvent::MouseMotion { dx: 1.0, dy: -1.0 },
+            RecordedEvent::MouseWheel {
+                x: 0.0,
+                y: 12.0,
+                pixels: true,
+            },
+        ];
+        for event in events {
+            let winit_event = event.to_event().unwrap();
+            assert_eq!(RecordedEvent::from_event(&winit_event), Some(event));
+        }
+
+        let touch = RecordedEvent::Touch {
+            id: 1,
+            phase: RecordedPhase::Moved,
+            x: 5.0,
+            y: 6.0,
+        };
+        assert_eq!(touch.to_event(), None);
+        assert_eq!(RecordedEvent::from_touch(touch.to_touch().unwrap()), touch);
+    }
+
+    #[test]
+    fn recorder_logs_only_changes() {
+        let mut recorder = Recorder::default();
+        recorder.record_gamepads(0, &[]);
+        recorder.record_gamepads(1, &[]);
+        recorder.record_gamepads(2, &[pad(0.0)]);
+        recorder.record_gamepads(3, &[pad(0.0)]);
+        recorder.record_gamepads(4, &[pad(1.0)]);
+        recorder.record_pointer_lock(0, false, false);
+        recorder.record_pointer_lock(1, false, false);
+        recorder.record_pointer_lock(2, true, false);
+        recorder.record_pointer_lock(3, true, true);
+        recorder.record_pointer_lock(4, true, true);
+        recorder.record(4, key("W", true));
+        recorder.record(4, key("W", true));
+
+        let ticks: Vec<(u64, &str)> = recorder
+            .log()
+            .events
+            .iter()
+            .map(|logged| {
+                let kind = match logged.event {
+                    RecordedEvent::Gamepads { .. } => "gamepads",
+                    RecordedEvent::PointerLock { .. } => "pointer_lock",
+                    _ => "other",
+                };
+                (logged.tick, kind)
+            })
+            .collect();
+        assert_eq!(
+            ticks,
+            vec![
+                (0, "gamepads"),
+                (2, "gamepads"),
+                (4, "gamepads"),
+                (0, "pointer_lock"),
+                (2, "pointer_lock"),
+                (3, "pointer_lock"),
+                // Events are always logged.
+                (4, "other"),
+                (4, "other"),
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_hands_out_events_by_tick() {
+        let mut replay = Replay::new(log(vec![
+            (0, key("A", true)),
+            (0, key("B", true)),
+            (2, key("C", true)),
+            (5, key("D", true)),
+        ]));
+
+        assert_eq!(replay.take_events(0), vec![key("A", true), key("B", true)]);
+        assert_eq!(replay.take_events(1), vec![]);
+        assert!(!replay.is_finished());
+        // A skipped tick's events come with the next one, still in order.
+        assert_eq!(replay.take_events(5), vec![key("C", true), key("D", true)]);
+        assert!(replay.is_finished());
+        assert_eq!(replay.take_events(6), vec![]);
+    }
+
+    #[test]
+    fn replay_keeps_polled_state() {
+        let mut replay = Replay::new(log(vec![
+            (
+                1,
+                RecordedEvent::Gamepads {
+                    pads: vec![pad(1.0)],
+                },
+            ),
+            (
+                1,
+                RecordedEvent::PointerLock {
+                    locked: true,
+                    fullscreen: false,
+                },
+            ),
+            (3, key("A", true)),
+            (
+                3,
+                RecordedEvent::PointerLock {
+                    locked: true,
+                    fullscreen: true,
+                },
+            ),
+            (4, RecordedEvent::Gamepads { pads: vec![] }),
+        ]));
+
+        assert_eq!(replay.take_events(0), vec![]);
+        assert!(replay.gamepads().is_empty());
+        assert!(!replay.is_pointer_locked());
+
+        assert_eq!(replay.take_events(1), vec![]);
+        assert_eq!(replay.gamepads(), vec![pad(1.0)]);
+        assert!(replay.is_pointer_locked());
+        assert!(!replay.is_fullscreen());
+
+        assert_eq!(replay.take_events(3), vec![key("A", true)]);
+        assert!(replay.is_fullscreen());
+
+        replay.take_events(4);
+        assert!(replay.gamepads().is_empty());
+        assert!(replay.is_finished());
+    }
+}
@@ -0,0 +1,88 @@
+use rg3d::{
+    core::{algebra::Vector3, pool::Handle},
+    engine::resource_manager::ResourceManager,
+    scene::{
+        base::BaseBuilder, camera::CameraBuilder, graph::Graph, node::Node,
+        transform::TransformBuilder, Scene,
+    },
+};
+
+use std::sync::Arc;
+
+use super::{
+    assets::{placeholder_skybox, AssetError, AssetTracker, Manifest},
+    level::SpawnPoint,
+    skybox::load_skybox,
+};
+
+/// A level as the loading future builds it, before it is added to the engine.
+pub struct LoadedScene {
+    pub scene: Scene,
+    /// Where players can enter the level, empty for editor scenes.
+    pub spawn_points: Vec<SpawnPoint>,
+}
+
+/// Hand-off point between the scene loading future and the game loop. The future fills `data`
+/// once everything is built or loading failed, the game loop takes it and adds it to the engine.
+pub struct SceneContext {
+    pub data: Option<Result<LoadedScene, AssetError>>,
+    /// The asset manifest the scene was built with, set before `data`.
+    pub manifest: Option<Arc<Manifest>>,
+}
+
+/// Creates a camera at given position with the skybox called `skybox`. Fails if the skybox
+/// can't be loaded.
+pub async fn create_camera(
+    resource_manager: ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    skybox: &str,
+    position: Vector3<f32>,
+    graph: &mut Graph,
+) -> Result<Handle<Node>, AssetError> {
+    let skybox = load_skybox(&resource_manager, tracker, manifest, skybox).await?;
+
+    // Camera is our eyes in the world - you won't see anything without it.
+    Ok(CameraBuilder::new(
+        BaseBuilder::new().with_local_transform(
+            TransformBuilder::new()
+                .with_local_position(position)
+                .build(),
+        ),
+    )
+    .with_skybox(skybox)
+    .build(graph))
+}
+
+/// Same as `create_camera`, but falls back to the placeholder skybox if the skybox is missing.
+/// The failed textures are left on the tracker's list.
+pub async fn create_camera_or_fallback(
+    resource_manager: ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    skybox: &str,
+    position: Vector3<f32>,
+    graph: &mut Graph,
+) -> Handle<Node> {
+    match create_camera(resource_manager, tracker, manifest, skybox, position, graph).await {
+        Ok(camera) => camera,
+        Err(_) => CameraBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            ),
+        )
+        .with_skybox(placeholder_skybox())
+        .build(graph),
+    }
+}
+
+/// First camera in the scene, there is one in every level once it's loaded.
+pub fn find_camera(scene: &Scene) -> Handle<Node> {
+    scene
+        .graph
+        .traverse_handle_iter(scene.graph.get_root())
+        .find(|&handle| matches!(scene.graph[handle], Node::Camera(_)))
+        .unwrap_or_default()
+}
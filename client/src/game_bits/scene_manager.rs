@@ -0,0 +1,169 @@
+// Moves the game from one level to the next, e.g. from the lobby to a match map, while it keeps
+// running. The next level loads in the background, then the screen fades to black, the scenes
+// are swapped and the old one is unloaded together with the resources only it used, and the
+// screen fades back in. States ask for a level by setting `GameContext::next_level`.
+
+use rg3d::{
+    core::pool::Handle,
+    gui::{
+        message::{MessageDirection, TextMessage},
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        HorizontalAlignment, Thickness, VerticalAlignment,
+    },
+};
+
+use std::sync::{Arc, Mutex};
+
+use super::{
+    app::{GameContext, UiNode},
+    assets::{AssetError, AssetTracker},
+    fade::ScreenFade,
+    game_scene::GameScene,
+    level_loader::load_level_scene,
+    scene_builder::{LoadedScene, SceneContext},
+};
+
+struct LevelLoad {
+    level: String,
+    context: Arc<Mutex<SceneContext>>,
+    // Loaded and waiting for the screen to go black.
+    scene: Option<LoadedScene>,
+}
+
+pub struct SceneManager {
+    fade: ScreenFade,
+    // Seconds to fade out, and again to fade back in.
+    fade_duration: f32,
+    load: Option<LevelLoad>,
+    error_text: Handle<UiNode>,
+}
+
+impl SceneManager {
+    pub fn new(fade_duration: f32) -> Self {
+        Self {
+            fade: ScreenFade::new(),
+            fade_duration,
+            load: None,
+            error_text: Handle::NONE,
+        }
+    }
+
+    /// Call once per time step.
+    pub fn update(&mut self, ctx: &mut GameContext) {
+        // A request that comes in during the fade waits for the swap, otherwise it replaces the
+        // level that is still loading.
+        let fading = self
+            .load
+            .as_ref()
+            .map_or(false, |load| load.scene.is_some());
+        if !fading {
+            if let Some(level) = ctx.next_level.take() {
+                self.start_loading(ctx, level);
+            }
+        }
+
+        let dt = ctx.clock.timestep();
+        self.fade.update(ctx, dt);
+
+        let data = match self.load.as_ref() {
+            Some(load) if load.scene.is_none() => load.context.lock().unwrap().data.take(),
+            _ => None,
+        };
+        match data {
+            Some(Ok(loaded)) => {
+                if let Some(load) = self.load.as_mut() {
+                    load.scene = Some(loaded);
+                }
+                self.fade.fade_out(self.fade_duration);
+            }
+            // The old level just keeps going.
+            Some(Err(err)) => {
+                self.load = None;
+                self.show_error(ctx, &err);
+            }
+            None => (),
+        }
+
+        if self.fade.is_black() {
+            if let Some(LevelLoad {
+                level,
+                scene: Some(loaded),
+                ..
+            }) = self.load.take()
+            {
+                swap_scene(ctx, loaded);
+                ctx.level = level;
+                self.clear_error(ctx);
+                self.fade.fade_in(self.fade_duration);
+            }
+        }
+    }
+
+    fn start_loading(&mut self, ctx: &mut GameContext, level: String) {
+        let context = Arc::new(Mutex::new(SceneContext {
+            data: None,
+            manifest: None,
+        }));
+        self.load = Some(LevelLoad {
+            level: level.clone(),
+            context: context.clone(),
+            scene: None,
+        });
+
+        let resource_manager = ctx.engine.resource_manager.clone();
+        let manifest = ctx.manifest.clone();
+        ctx.platform.spawn(Box::pin(async move {
+            let result =
+                load_level_scene(resource_manager, AssetTracker::new(), manifest, level).await;
+            context.lock().unwrap().data = Some(result);
+        }));
+    }
+
+    fn show_error(&mut self, ctx: &mut GameContext, err: &AssetError) {
+        if self.error_text.is_none() {
+            self.error_text = TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_margin(Thickness::uniform(4.0))
+                    .with_horizontal_alignment(HorizontalAlignment::Right)
+                    .with_vertical_alignment(VerticalAlignment::Bottom),
+            )
+            .build(&mut ctx.engine.user_interface.build_ctx());
+        }
+
+        ctx.engine.user_interface.send_message(TextMessage::text(
+            self.error_text,
+            MessageDirection::ToWidget,
+            format!("Failed to change level: {}", err),
+        ));
+    }
+
+    fn clear_error(&mut self, ctx: &mut GameContext) {
+        if self.error_text.is_some() {
+            ctx.engine.user_interface.send_message(TextMessage::text(
+                self.error_text,
+                MessageDirection::ToWidget,
+                String::new(),
+            ));
+        }
+    }
+}
+
+/// Makes `loaded` the current level and unloads the one it replaces.
+pub fn swap_scene(ctx: &mut GameContext, loaded: LoadedScene) {
+    let mut game_scene = GameScene::new(&mut ctx.engine, loaded);
+    if let Some(old) = ctx.scene.take() {
+        // Everyone who was in the old level comes along to the new one.
+        game_scene.local_player = old.local_player;
+        game_scene.paused = old.paused;
+        game_scene.set_camera_mode(&mut ctx.engine, old.camera_mode());
+        for &client_id in old.players.keys() {
+            game_scene.spawn_player(&mut ctx.engine, client_id);
+        }
+
+        ctx.engine.scenes.remove(old.scene);
+        // The old scene held the last references to the models and textures only it used.
+        ctx.engine.resource_manager.state().purge_unused_resources();
+    }
+    ctx.scene = Some(game_scene);
+}
@@ -0,0 +1,224 @@
+// Skyboxes are defined by name in the asset manifest, six faces plus how their textures wrap, so
+// levels and times of day can each pick their own sky.
+
+use rg3d::{
+    core::pool::Handle,
+    engine::resource_manager::ResourceManager,
+    resource::texture::{Texture, TextureWrapMode},
+    scene::{camera::SkyBox, node::Node, Scene},
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::sync::{Arc, Mutex};
+
+use super::{
+    app::GameContext,
+    assets::{AssetError, AssetTracker, Manifest},
+    fade::ScreenFade,
+};
+
+/// The sky of the built-in test level and of levels without a sky of their own.
+pub const DEFAULT_SKYBOX: &str = "dark_stormy";
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkyboxWrap {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat,
+}
+
+impl Default for SkyboxWrap {
+    // ClampToEdge removes any seams on the edges of the skybox.
+    fn default() -> Self {
+        SkyboxWrap::ClampToEdge
+    }
+}
+
+impl From<SkyboxWrap> for TextureWrapMode {
+    fn from(wrap: SkyboxWrap) -> Self {
+        match wrap {
+            SkyboxWrap::ClampToEdge => TextureWrapMode::ClampToEdge,
+            SkyboxWrap::Repeat => TextureWrapMode::Repeat,
+            SkyboxWrap::MirroredRepeat => TextureWrapMode::MirroredRepeat,
+        }
+    }
+}
+
+/// Faces are texture ids from the manifest.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SkyboxDefinition {
+    pub front: String,
+    pub back: String,
+    pub left: String,
+    pub right: String,
+    pub top: String,
+    pub bottom: String,
+    #[serde(default)]
+    pub wrap_s: SkyboxWrap,
+    #[serde(default)]
+    pub wrap_t: SkyboxWrap,
+}
+
+impl SkyboxDefinition {
+    pub fn faces(&self) -> [&str; 6] {
+        [
+            &self.front,
+            &self.back,
+            &self.left,
+            &self.right,
+            &self.top,
+            &self.bottom,
+        ]
+    }
+}
+
+async fn request_face(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    id: &str,
+) -> Result<Texture, AssetError> {
+    let url = manifest
+        .url(id)
+        .map_err(|err| tracker.record_failure(err))?;
+    tracker.texture(resource_manager, &url).await
+}
+
+/// Loads the skybox called `name`. Fails if it isn't defined or any of its faces can't be loaded.
+pub async fn load_skybox(
+    resource_manager: &ResourceManager,
+    tracker: &AssetTracker,
+    manifest: &Manifest,
+    name: &str,
+) -> Result<SkyBox, AssetError> {
+    let definition = manifest.skyboxes.get(name).ok_or_else(|| {
+        tracker.record_failure(AssetError::UnknownId {
+            id: name.to_string(),
+        })
+    })?;
+
+    // Load the faces in parallel.
+    let (front, back, left, right, top, bottom) = rg3d::core::futures::join!(
+        request_face(resource_manager, tracker, manifest, &definition.front),
+        request_face(resource_manager, tracker, manifest, &definition.back),
+        request_face(resource_manager, tracker, manifest, &definition.left),
+        request_face(resource_manager, tracker, manifest, &definition.right),
+        request_face(resource_manager, tracker, manifest, &definition.top),
+        request_face(resource_manager, tracker, manifest, &definition.bottom)
+    );
+
+    let skybox = SkyBox {
+        front: Some(front?),
+        back: Some(back?),
+        left: Some(left?),
+        right: Some(right?),
+        top: Some(top?),
+        bottom: Some(bottom?),
+    };
+
+    for skybox_texture in skybox.textures().iter().filter_map(|t| t.clone()) {
+        set_face_wrap(&skybox_texture, definition);
+    }
+
+    Ok(skybox)
+}
+
+/// Gives a face texture the wrap modes of its skybox. Loading the texture again resets them.
+pub fn set_face_wrap(texture: &Texture, definition: &SkyboxDefinition) {
+    let mut data = texture.data_ref();
+    data.set_s_wrap_mode(definition.wrap_s.into());
+    data.set_t_wrap_mode(definition.wrap_t.into());
+}
+
+/// Replaces the skybox of `camera` right away, None leaves just the clear color.
+pub fn set_skybox(scene: &mut Scene, camera: Handle<Node>, skybox: Option<SkyBox>) {
+    scene.graph[camera].as_camera_mut().set_skybox(skybox);
+}
+
+type PendingSkybox = Arc<Mutex<Option<Result<SkyBox, AssetError>>>>;
+
+/// Swaps the skybox of a camera behind a fade to black. The new skybox loads while the screen
+/// darkens and is put in once both are done, then the screen fades back in.
+pub struct SkyboxSwitcher {
+    fade: ScreenFade,
+    fade_duration: f32,
+    pending: Option<PendingSkybox>,
+    last_error: Option<AssetError>,
+}
+
+impl SkyboxSwitcher {
+    pub fn new(fade_duration: f32) -> Self {
+        Self {
+            fade: ScreenFade::new(),
+            fade_duration,
+            pending: None,
+            last_error: None,
+        }
+    }
+
+    pub fn is_switching(&self) -> bool {
+        self.pending.is_some() || !self.fade.is_finished()
+    }
+
+    /// Why the last switch didn't happen, the old skybox stays in that case.
+    pub fn last_error(&self) -> Option<&AssetError> {
+        self.last_error.as_ref()
+    }
+
+    /// Starts switching to the skybox called `name`. A switch that is still loading gets
+    /// replaced.
+    pub fn switch_to(&mut self, ctx: &mut GameContext, name: &str) {
+        let pending = PendingSkybox::default();
+        self.pending = Some(pending.clone());
+        self.fade.fade_out(self.fade_duration);
+
+        let resource_manager = ctx.engine.resource_manager.clone();
+        let manifest = ctx.manifest.clone();
+        let name = name.to_string();
+        ctx.platform.spawn(Box::pin(async move {
+            let tracker = AssetTracker::new();
+            let result = load_skybox(&resource_manager, &tracker, &manifest, &name).await;
+            *pending.lock().unwrap() = Some(result);
+        }));
+    }
+
+    /// Call once per time step, the skybox goes to the camera of the current level.
+    pub fn update(&mut self, ctx: &mut GameContext) {
+        let dt = ctx.clock.timestep();
+        self.fade.update(ctx, dt);
+
+        if !self.fade.is_black() {
+            return;
+        }
+
+        let loaded = self
+            .pending
+            .as_ref()
+            .and_then(|pending| pending.lock().unwrap().take());
+        if let Some(result) = loaded {
+            self.pending = None;
+            match result {
+                Ok(skybox) => {
+                    self.last_error = None;
+                    if let Some(game_scene) = ctx.scene.as_ref() {
+                        set_skybox(
+                            &mut ctx.engine.scenes[game_scene.scene],
+                            game_scene.camera,
+                            Some(skybox),
+                        );
+                    }
+                }
+                Err(err) => self.last_error = Some(err),
+            }
+            self.fade.fade_in(self.fade_duration);
+        }
+    }
+
+    /// Removes the fade overlay, for when the owner goes away mid-switch.
+    pub fn cancel(&mut self, ctx: &mut GameContext) {
+        self.pending = None;
+        self.fade.clear(ctx);
+    }
+}
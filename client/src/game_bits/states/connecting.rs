@@ -0,0 +1,74 @@
+use rg3d::core::pool::Handle;
+
+use super::{
+    build_button, build_centered_panel, build_label, is_click, remove_widget, DisconnectedState,
+    GameState, MainMenuState, PlayingState, Transition,
+};
+use crate::game_bits::{
+    app::{GameContext, UiMessage, UiNode},
+    net::ConnectionStatus,
+};
+
+// Give up if the server hasn't welcomed us after this many seconds.
+const CONNECT_TIMEOUT: f32 = 10.0;
+
+/// Opens the connection and waits for the server's welcome.
+pub struct ConnectingState {
+    panel: Handle<UiNode>,
+    cancel: Handle<UiNode>,
+    elapsed: f32,
+}
+
+impl ConnectingState {
+    pub fn new() -> Self {
+        Self {
+            panel: Handle::NONE,
+            cancel: Handle::NONE,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl GameState for ConnectingState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+        let text = build_label(build_ctx, &format!("Connecting to {}", ctx.config.server_url));
+        self.cancel = build_button(build_ctx, "Cancel");
+        self.panel = build_centered_panel(build_ctx, &[text, self.cancel]);
+
+        ctx.connection = Some(ctx.platform.connect(&ctx.config.server_url));
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        remove_widget(ctx, self.panel);
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        self.elapsed += ctx.clock.timestep();
+
+        let status = match ctx.connection.as_ref() {
+            Some(connection) => connection.status(),
+            None => ConnectionStatus::Closed("not connected".to_string()),
+        };
+
+        match status {
+            ConnectionStatus::Connected => Transition::Switch(Box::new(PlayingState::new())),
+            ConnectionStatus::Closed(reason) => {
+                Transition::Switch(Box::new(DisconnectedState::new(reason)))
+            }
+            ConnectionStatus::Connecting if self.elapsed > CONNECT_TIMEOUT => Transition::Switch(
+                Box::new(DisconnectedState::new("timed out".to_string())),
+            ),
+            ConnectionStatus::Connecting => Transition::None,
+        }
+    }
+
+    fn handle_ui_message(&mut self, ctx: &mut GameContext, message: &UiMessage) -> Transition {
+        if is_click(message, self.cancel) {
+            ctx.connection = None;
+            Transition::Switch(Box::new(MainMenuState::new()))
+        } else {
+            Transition::None
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use rg3d::{
+    core::pool::Handle,
+    event::VirtualKeyCode,
+    gui::{
+        message::{MessageDirection, TextMessage},
+        scroll_viewer::ScrollViewerBuilder,
+        stack_panel::StackPanelBuilder,
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        Orientation, Thickness, VerticalAlignment,
+    },
+};
+
+use super::{
+    build_button, build_centered_panel, build_label, is_click, remove_widget, GameState,
+    MainMenuState, Transition,
+};
+use crate::game_bits::{
+    app::{GameContext, UiMessage, UiNode},
+    input::{Action, CapturedKey},
+    key_bindings::{key_name, KeyBindings, CANCEL_KEY},
+};
+
+// Pixels, the list of actions scrolls beyond it.
+const ROWS_HEIGHT: f32 = 400.0;
+
+struct BindingRow {
+    action: Action,
+    keys: Handle<UiNode>,
+    rebind: Handle<UiNode>,
+}
+
+/// Lists the key of each action, clicking an action's button binds it to the next key pressed.
+pub struct ControlsState {
+    panel: Handle<UiNode>,
+    rows: Vec<BindingRow>,
+    status: Handle<UiNode>,
+    reset: Handle<UiNode>,
+    back: Handle<UiNode>,
+    // The action waiting for its new key.
+    rebinding: Option<Action>,
+}
+
+impl ControlsState {
+    pub fn new() -> Self {
+        Self {
+            panel: Handle::NONE,
+            rows: Vec::new(),
+            status: Handle::NONE,
+            reset: Handle::NONE,
+            back: Handle::NONE,
+            rebinding: None,
+        }
+    }
+
+    fn show_status(&self, ctx: &mut GameContext, text: &str) {
+        ctx.engine.user_interface.send_message(TextMessage::text(
+            self.status,
+            MessageDirection::ToWidget,
+            text.to_string(),
+        ));
+    }
+
+    fn show_bindings(&self, ctx: &mut GameContext) {
+        for row in self.rows.iter() {
+            let names = ctx.input.bindings.key_names(row.action);
+            let text = if names.is_empty() {
+                format!("{}: -", row.action.label())
+            } else {
+                format!("{}: {}", row.action.label(), names.join(", "))
+            };
+
+            ctx.engine.user_interface.send_message(TextMessage::text(
+                row.keys,
+                MessageDirection::ToWidget,
+                text,
+            ));
+        }
+    }
+
+    fn start_rebinding(&mut self, ctx: &mut GameContext, action: Action) {
+        self.rebinding = Some(action);
+        ctx.input.capture_next_key();
+        self.show_prompt(ctx, &format!("Press a key for {}", action.label()));
+    }
+
+    // Status text for while a key is being captured, with how to get out of it.
+    fn show_prompt(&self, ctx: &mut GameContext, text: &str) {
+        let cancel = key_name(CANCEL_KEY).unwrap_or_default();
+        self.show_status(ctx, &format!("{} ({} to cancel)", text, cancel));
+    }
+
+    fn cancel_rebinding(&mut self, ctx: &mut GameContext) {
+        self.rebinding = None;
+        ctx.input.cancel_capture();
+        self.show_status(ctx, "");
+    }
+
+    fn finish_rebinding(&mut self, ctx: &mut GameContext, action: Action, key: VirtualKeyCode) {
+        let name = match key_name(key) {
+            Some(name) => name,
+            None => {
+                ctx.input.capture_next_key();
+                self.show_prompt(ctx, "That key can't be bound, press another one");
+                return;
+            }
+        };
+
+        if let Err(other) = ctx.input.bindings.rebind(action, key) {
+            ctx.input.capture_next_key();
+            self.show_prompt(
+                ctx,
+                &format!(
+                    "{} is already used for {}, press another key",
+                    name,
+                    other.label()
+                ),
+            );
+            return;
+        }
+
+        self.rebinding = None;
+        ctx.input.bindings.save(&*ctx.platform);
+        self.show_bindings(ctx);
+        self.show_status(ctx, "");
+    }
+}
+
+impl GameState for ControlsState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+        let title = build_label(build_ctx, "Controls");
+
+        let mut rows = Vec::new();
+        for action in Action::ALL.iter() {
+            let keys = TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_width(200.0)
+                    .with_margin(Thickness::uniform(4.0))
+                    .with_vertical_alignment(VerticalAlignment::Center),
+            )
+            .build(build_ctx);
+            let rebind = build_button(build_ctx, "Rebind");
+            let row =
+                StackPanelBuilder::new(WidgetBuilder::new().with_child(keys).with_child(rebind))
+                    .with_orientation(Orientation::Horizontal)
+                    .build(build_ctx);
+
+            rows.push(row);
+            self.rows.push(BindingRow {
+                action: *action,
+                keys,
+                rebind,
+            });
+        }
+
+        // There are more actions than fit on small screens.
+        let mut list = WidgetBuilder::new();
+        for row in rows {
+            list = list.with_child(row);
+        }
+        let list = ScrollViewerBuilder::new(WidgetBuilder::new().with_height(ROWS_HEIGHT))
+            .with_content(StackPanelBuilder::new(list).build(build_ctx))
+            .build(build_ctx);
+
+        self.status = build_label(build_ctx, "");
+        self.reset = build_button(build_ctx, "Reset to defaults");
+        self.back = build_button(build_ctx, "Back");
+        self.panel = build_centered_panel(
+            build_ctx,
+            &[title, list, self.status, self.reset, self.back],
+        );
+
+        self.show_bindings(ctx);
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        ctx.input.cancel_capture();
+        remove_widget(ctx, self.panel);
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        match self.rebinding {
+            Some(action) => match ctx.input.take_captured_key() {
+                Some(CapturedKey::Key(key)) => self.finish_rebinding(ctx, action, key),
+                Some(CapturedKey::Cancelled) => self.cancel_rebinding(ctx),
+                None => (),
+            },
+            // Captured keys don't count as pressed, so this is never the key just bound or the
+            // key that cancelled rebinding.
+            None if ctx.input.was_action_pressed(Action::Menu) => {
+                return Transition::Switch(Box::new(MainMenuState::new()));
+            }
+            None => (),
+        }
+
+        Transition::None
+    }
+
+    fn handle_ui_message(&mut self, ctx: &mut GameContext, message: &UiMessage) -> Transition {
+        if is_click(message, self.back) {
+            return Transition::Switch(Box::new(MainMenuState::new()));
+        }
+
+        if is_click(message, self.reset) {
+            self.cancel_rebinding(ctx);
+            ctx.input.bindings = KeyBindings::default();
+            ctx.input.bindings.save(&*ctx.platform);
+            self.show_bindings(ctx);
+            return Transition::None;
+        }
+
+        let clicked = self
+            .rows
+            .iter()
+            .find(|row| is_click(message, row.rebind))
+            .map(|row| row.action);
+        if let Some(action) = clicked {
+            self.start_rebinding(ctx, action);
+        }
+
+        Transition::None
+    }
+}
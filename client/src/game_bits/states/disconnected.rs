@@ -0,0 +1,56 @@
+use rg3d::core::pool::Handle;
+
+use super::{
+    build_button, build_centered_panel, build_label, is_click, remove_widget, ConnectingState,
+    GameState, MainMenuState, Transition,
+};
+use crate::game_bits::app::{GameContext, UiMessage, UiNode};
+
+/// Shown when the connection failed or dropped, offers to try again.
+pub struct DisconnectedState {
+    reason: String,
+    panel: Handle<UiNode>,
+    reconnect: Handle<UiNode>,
+    main_menu: Handle<UiNode>,
+}
+
+impl DisconnectedState {
+    pub fn new(reason: String) -> Self {
+        Self {
+            reason,
+            panel: Handle::NONE,
+            reconnect: Handle::NONE,
+            main_menu: Handle::NONE,
+        }
+    }
+}
+
+impl GameState for DisconnectedState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        ctx.connection = None;
+
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+        let text = build_label(build_ctx, &format!("Disconnected: {}", self.reason));
+        self.reconnect = build_button(build_ctx, "Reconnect");
+        self.main_menu = build_button(build_ctx, "Main menu");
+        self.panel = build_centered_panel(build_ctx, &[text, self.reconnect, self.main_menu]);
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        remove_widget(ctx, self.panel);
+    }
+
+    fn update(&mut self, _ctx: &mut GameContext) -> Transition {
+        Transition::None
+    }
+
+    fn handle_ui_message(&mut self, _ctx: &mut GameContext, message: &UiMessage) -> Transition {
+        if is_click(message, self.reconnect) {
+            Transition::Switch(Box::new(ConnectingState::new()))
+        } else if is_click(message, self.main_menu) {
+            Transition::Switch(Box::new(MainMenuState::new()))
+        } else {
+            Transition::None
+        }
+    }
+}
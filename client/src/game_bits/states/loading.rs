@@ -0,0 +1,186 @@
+use rg3d::{
+    core::pool::Handle,
+    gui::{
+        message::{MessageDirection, ProgressBarMessage, TextMessage},
+        progress_bar::ProgressBarBuilder,
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        HorizontalAlignment, Thickness, VerticalAlignment,
+    },
+};
+
+use std::sync::{Arc, Mutex};
+
+use super::{
+    build_centered_panel, build_label, remove_widget, GameState, MainMenuState, Transition,
+};
+use crate::game_bits::{
+    app::{GameContext, UiNode},
+    assets::{preload, AssetProgress, AssetTracker, Manifest},
+    level::DEFAULT_LEVEL,
+    level_loader::load_level_scene,
+    scene_builder::SceneContext,
+    scene_manager::swap_scene,
+};
+
+/// Loads the level in the background while showing how far it got, then moves on to the
+/// main menu.
+pub struct LoadingState {
+    load_context: Arc<Mutex<SceneContext>>,
+    tracker: AssetTracker,
+    panel: Handle<UiNode>,
+    text: Handle<UiNode>,
+    progress_bar: Handle<UiNode>,
+    failed: bool,
+}
+
+impl LoadingState {
+    pub fn new() -> Self {
+        Self {
+            load_context: Arc::new(Mutex::new(SceneContext {
+                data: None,
+                manifest: None,
+            })),
+            tracker: AssetTracker::new(),
+            panel: Handle::NONE,
+            text: Handle::NONE,
+            progress_bar: Handle::NONE,
+            failed: false,
+        }
+    }
+
+    fn show_progress(&self, ctx: &mut GameContext, progress: &AssetProgress) {
+        let mut text = format!(
+            "Loading assets {}/{}",
+            progress.completed + progress.failed.len(),
+            progress.requested
+        );
+        if !progress.failed.is_empty() {
+            text += &format!(", {} failed", progress.failed.len());
+        }
+        if let Some(path) = progress.last_finished.as_ref() {
+            text += &format!("\n{}", path);
+        }
+
+        let ui = &mut ctx.engine.user_interface;
+        ui.send_message(TextMessage::text(
+            self.text,
+            MessageDirection::ToWidget,
+            text,
+        ));
+        ui.send_message(ProgressBarMessage::progress(
+            self.progress_bar,
+            MessageDirection::ToWidget,
+            progress.fraction(),
+        ));
+    }
+
+    // Assets that failed but had a fallback stay listed in the corner of the screen, so a
+    // magenta checker board in the level can be traced back to its file.
+    fn show_failed_assets(&self, ctx: &mut GameContext, progress: &AssetProgress) {
+        if progress.failed.is_empty() {
+            return;
+        }
+
+        let mut text = "Failed to load:".to_string();
+        for err in progress.failed.iter() {
+            text += &format!("\n{}", err.path());
+        }
+
+        TextBuilder::new(
+            WidgetBuilder::new()
+                .with_margin(Thickness::uniform(4.0))
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_vertical_alignment(VerticalAlignment::Bottom),
+        )
+        .with_text(&text)
+        .build(&mut ctx.engine.user_interface.build_ctx());
+    }
+}
+
+impl GameState for LoadingState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+        self.text = build_label(build_ctx, "Loading...");
+        self.progress_bar = ProgressBarBuilder::new(
+            WidgetBuilder::new()
+                .with_height(20.0)
+                .with_margin(Thickness::uniform(4.0)),
+        )
+        .build(build_ctx);
+        self.panel = build_centered_panel(build_ctx, &[self.text, self.progress_bar]);
+
+        let resource_manager = ctx.engine.resource_manager.clone();
+        let tracker = self.tracker.clone();
+        let load_context = self.load_context.clone();
+        let level = ctx.config.level.clone();
+        // The manifest itself must never come from the cache, it's what tells the browser which
+        // asset versions are current.
+        let manifest_url = ctx.platform.uncached_url(&ctx.config.manifest_url);
+        let hashed_urls = ctx.config.hashed_asset_urls;
+        ctx.platform.spawn(Box::pin(async move {
+            // Without a manifest every asset gets its placeholder, which still beats no game.
+            let manifest = Manifest::load(&tracker, &manifest_url, hashed_urls)
+                .await
+                .unwrap_or_default();
+            let manifest = Arc::new(manifest);
+            load_context.lock().unwrap().manifest = Some(manifest.clone());
+
+            let groups: &[&str] = match level {
+                Some(_) => &["skybox.dark_stormy"],
+                None => &["skybox.dark_stormy", "test_level", "barrel"],
+            };
+            preload(&resource_manager, &tracker, &manifest, groups).await;
+
+            let level = level.unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+            let result = load_level_scene(resource_manager, tracker, manifest, level).await;
+            load_context.lock().unwrap().data = Some(result);
+        }));
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        remove_widget(ctx, self.panel);
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if self.failed {
+            return Transition::None;
+        }
+
+        let progress = self.tracker.progress();
+        self.show_progress(ctx, &progress);
+
+        let (data, manifest) = {
+            let mut load_context = self.load_context.lock().unwrap();
+            (load_context.data.take(), load_context.manifest.clone())
+        };
+
+        match data {
+            Some(Ok(loaded)) => {
+                if let Some(manifest) = manifest {
+                    ctx.manifest = manifest;
+                }
+                // Fresh snapshot, the future may have finished after `progress` was taken.
+                self.show_failed_assets(ctx, &self.tracker.progress());
+                swap_scene(ctx, loaded);
+                ctx.level = ctx
+                    .config
+                    .level
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+                Transition::Switch(Box::new(MainMenuState::new()))
+            }
+            Some(Err(err)) => {
+                // Nothing to play without a level, leave the error on screen.
+                self.failed = true;
+                ctx.engine.user_interface.send_message(TextMessage::text(
+                    self.text,
+                    MessageDirection::ToWidget,
+                    err.to_string(),
+                ));
+                Transition::None
+            }
+            None => Transition::None,
+        }
+    }
+}
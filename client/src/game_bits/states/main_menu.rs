@@ -0,0 +1,51 @@
+use rg3d::core::pool::Handle;
+
+use super::{
+    build_button, build_centered_panel, build_label, is_click, remove_widget, ConnectingState,
+    ControlsState, GameState, Transition,
+};
+use crate::game_bits::app::{GameContext, UiMessage, UiNode};
+
+pub struct MainMenuState {
+    panel: Handle<UiNode>,
+    play: Handle<UiNode>,
+    controls: Handle<UiNode>,
+}
+
+impl MainMenuState {
+    pub fn new() -> Self {
+        Self {
+            panel: Handle::NONE,
+            play: Handle::NONE,
+            controls: Handle::NONE,
+        }
+    }
+}
+
+impl GameState for MainMenuState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+        let title = build_label(build_ctx, "Gorust!");
+        self.play = build_button(build_ctx, "Play");
+        self.controls = build_button(build_ctx, "Controls");
+        self.panel = build_centered_panel(build_ctx, &[title, self.play, self.controls]);
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        remove_widget(ctx, self.panel);
+    }
+
+    fn update(&mut self, _ctx: &mut GameContext) -> Transition {
+        Transition::None
+    }
+
+    fn handle_ui_message(&mut self, _ctx: &mut GameContext, message: &UiMessage) -> Transition {
+        if is_click(message, self.play) {
+            Transition::Switch(Box::new(ConnectingState::new()))
+        } else if is_click(message, self.controls) {
+            Transition::Switch(Box::new(ControlsState::new()))
+        } else {
+            Transition::None
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// The flow of the game: loading -> main menu -> connecting -> playing, with a disconnected screen
+// that leads back to connecting or the menu, and a controls screen off the main menu. Only the
+// state on top of the stack gets updates and events, states below it wait until it is popped, as
+// the game does under the pause overlay. The menu key (Escape, Start on gamepads) goes back a
+// screen: from the game to the pause overlay, from there to the main menu.
+
+mod connecting;
+mod controls;
+mod disconnected;
+mod loading;
+mod main_menu;
+mod paused;
+mod playing;
+
+pub use self::{
+    connecting::ConnectingState, controls::ControlsState, disconnected::DisconnectedState,
+    loading::LoadingState, main_menu::MainMenuState, paused::PausedState, playing::PlayingState,
+};
+
+use rg3d::{
+    core::pool::Handle,
+    event::Event,
+    gui::{
+        button::ButtonBuilder,
+        message::{ButtonMessage, MessageDirection, UiMessageData, WidgetMessage},
+        stack_panel::StackPanelBuilder,
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        HorizontalAlignment, Thickness, VerticalAlignment,
+    },
+};
+
+use super::app::{BuildContext, GameContext, UiMessage, UiNode};
+
+/// What the state stack should do after a state callback.
+pub enum Transition {
+    None,
+    /// Put a new state on top of the current one, e.g. an overlay.
+    Push(Box<dyn GameState>),
+    /// Remove the current state and return to the one below.
+    Pop,
+    /// Replace the current state.
+    Switch(Box<dyn GameState>),
+    /// Remove every state and start over with a new one, e.g. leaving the game from the pause
+    /// overlay.
+    Reset(Box<dyn GameState>),
+}
+
+pub trait GameState {
+    /// Called when the state becomes active, build its UI here.
+    fn enter(&mut self, ctx: &mut GameContext);
+
+    /// Called when the state is removed from the stack, clean up its UI here.
+    fn exit(&mut self, ctx: &mut GameContext);
+
+    /// Called once per fixed time step.
+    fn update(&mut self, ctx: &mut GameContext) -> Transition;
+
+    /// Raw window and device events, after the UI has seen them.
+    fn handle_event(&mut self, _ctx: &mut GameContext, _event: &Event<()>) -> Transition {
+        Transition::None
+    }
+
+    /// Messages coming back from the UI, button clicks and such.
+    fn handle_ui_message(&mut self, _ctx: &mut GameContext, _message: &UiMessage) -> Transition {
+        Transition::None
+    }
+}
+
+pub struct StateStack {
+    states: Vec<Box<dyn GameState>>,
+}
+
+impl StateStack {
+    pub fn new() -> Self {
+        Self { states: Vec::new() }
+    }
+
+    pub fn push(&mut self, ctx: &mut GameContext, mut state: Box<dyn GameState>) {
+        state.enter(ctx);
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self, ctx: &mut GameContext) {
+        if let Some(mut state) = self.states.pop() {
+            state.exit(ctx);
+        }
+    }
+
+    pub fn switch(&mut self, ctx: &mut GameContext, state: Box<dyn GameState>) {
+        self.pop(ctx);
+        self.push(ctx, state);
+    }
+
+    pub fn reset(&mut self, ctx: &mut GameContext, state: Box<dyn GameState>) {
+        while !self.is_empty() {
+            self.pop(ctx);
+        }
+        self.push(ctx, state);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn update(&mut self, ctx: &mut GameContext) {
+        if let Some(state) = self.states.last_mut() {
+            let transition = state.update(ctx);
+            self.apply(ctx, transition);
+        }
+    }
+
+    pub fn handle_event(&mut self, ctx: &mut GameContext, event: &Event<()>) {
+        if let Some(state) = self.states.last_mut() {
+            let transition = state.handle_event(ctx, event);
+            self.apply(ctx, transition);
+        }
+    }
+
+    pub fn handle_ui_message(&mut self, ctx: &mut GameContext, message: &UiMessage) {
+        if let Some(state) = self.states.last_mut() {
+            let transition = state.handle_ui_message(ctx, message);
+            self.apply(ctx, transition);
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut GameContext, transition: Transition) {
+        match transition {
+            Transition::None => (),
+            Transition::Push(state) => self.push(ctx, state),
+            Transition::Pop => self.pop(ctx),
+            Transition::Switch(state) => self.switch(ctx, state),
+            Transition::Reset(state) => self.reset(ctx, state),
+        }
+    }
+}
+
+// Small helpers for the menu-like screens, they all are a column of widgets in the middle of
+// the screen.
+
+pub fn build_centered_panel(ctx: &mut BuildContext, children: &[Handle<UiNode>]) -> Handle<UiNode> {
+    let mut widget = WidgetBuilder::new()
+        .with_width(300.0)
+        .with_horizontal_alignment(HorizontalAlignment::Center)
+        .with_vertical_alignment(VerticalAlignment::Center);
+    for child in children {
+        widget = widget.with_child(*child);
+    }
+    StackPanelBuilder::new(widget).build(ctx)
+}
+
+pub fn build_label(ctx: &mut BuildContext, text: &str) -> Handle<UiNode> {
+    TextBuilder::new(WidgetBuilder::new().with_margin(Thickness::uniform(4.0)))
+        .with_text(text)
+        .build(ctx)
+}
+
+pub fn build_button(ctx: &mut BuildContext, text: &str) -> Handle<UiNode> {
+    ButtonBuilder::new(
+        WidgetBuilder::new()
+            .with_height(32.0)
+            .with_margin(Thickness::uniform(4.0)),
+    )
+    .with_text(text)
+    .build(ctx)
+}
+
+pub fn remove_widget(ctx: &mut GameContext, widget: Handle<UiNode>) {
+    ctx.engine
+        .user_interface
+        .send_message(WidgetMessage::remove(widget, MessageDirection::ToWidget));
+}
+
+pub fn is_click(message: &UiMessage, button: Handle<UiNode>) -> bool {
+    message.destination() == button
+        && matches!(message.data(), UiMessageData::Button(ButtonMessage::Click))
+}
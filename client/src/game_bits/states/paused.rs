@@ -0,0 +1,109 @@
+use rg3d::{
+    core::pool::Handle,
+    event::{ElementState, Event, WindowEvent},
+};
+
+use super::{
+    build_centered_panel, build_label, remove_widget, GameState, MainMenuState, Transition,
+};
+use crate::game_bits::{
+    app::{GameContext, UiNode},
+    input::Action,
+    key_bindings::key_name,
+    net::ConnectionStatus,
+    pointer_lock::PointerLockEvent,
+};
+
+/// Pushed over the game while the pointer isn't locked or after the menu key, a click locks it
+/// and resumes, the menu key again leaves for the main menu.
+pub struct PausedState {
+    panel: Handle<UiNode>,
+}
+
+impl PausedState {
+    pub fn new() -> Self {
+        Self {
+            panel: Handle::NONE,
+        }
+    }
+}
+
+impl GameState for PausedState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            game_scene.paused = true;
+        }
+
+        let menu_key = ctx
+            .input
+            .bindings
+            .keys(Action::Menu)
+            .filter_map(key_name)
+            .min()
+            .unwrap_or("the menu key");
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+        let text = build_label(build_ctx, "Paused");
+        let hint = build_label(
+            build_ctx,
+            &format!("Click to resume, {} for the main menu", menu_key),
+        );
+        self.panel = build_centered_panel(build_ctx, &[text, hint]);
+
+        // Paused with the menu key the pointer is still locked, the cursor is needed to click
+        // and there would be no lock event to resume on.
+        ctx.pointer_lock
+            .exit_lock(&*ctx.platform, ctx.engine.get_window());
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        remove_widget(ctx, self.panel);
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            game_scene.paused = false;
+        }
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if ctx.input.was_action_pressed(Action::Menu) {
+            // Leaving the game, the server sees us go.
+            ctx.connection = None;
+            return Transition::Reset(Box::new(MainMenuState::new()));
+        }
+
+        let resumed = ctx
+            .pointer_lock
+            .events()
+            .contains(&PointerLockEvent::Locked);
+        // Touch screens have nothing to lock, a touch resumes. A dropped connection is for the
+        // game below to handle.
+        let closed = matches!(
+            ctx.connection
+                .as_ref()
+                .map(|connection| connection.status()),
+            Some(ConnectionStatus::Closed(_))
+        );
+        if resumed || ctx.input.touch.is_active() || closed {
+            Transition::Pop
+        } else {
+            Transition::None
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GameContext, event: &Event<()>) -> Transition {
+        // Only on a click, the player may have switched to another window and not want the
+        // pointer taken away. Browsers only grant it during a click anyway.
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        {
+            ctx.pointer_lock
+                .request_lock(&*ctx.platform, ctx.engine.get_window());
+        }
+
+        Transition::None
+    }
+}
@@ -0,0 +1,366 @@
+use rg3d::{
+    core::{
+        algebra::{Vector2, Vector3},
+        pool::Handle,
+    },
+    dpi::LogicalPosition,
+    event::{DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, WindowEvent},
+    gui::{
+        message::{MessageDirection, TextMessage},
+        text::TextBuilder,
+        widget::WidgetBuilder,
+    },
+};
+
+use super::{remove_widget, DisconnectedState, GameState, PausedState, Transition};
+use crate::game_bits::{
+    app::{GameContext, UiNode},
+    gamepad::GamepadEvent,
+    input::Action,
+    net::ConnectionStatus,
+    picking::{self, Highlight, Pick},
+    protocol::OutgoingMessage,
+    skybox::SkyboxSwitcher,
+    touch::TouchOverlay,
+};
+
+// Seconds to fade to black and back when switching skyboxes.
+const SKYBOX_FADE: f32 = 0.5;
+// Touchpads scroll by pixels instead of wheel steps.
+const PIXELS_PER_ZOOM_STEP: f32 = 50.0;
+// Newton seconds a click pushes props with.
+const PUSH_IMPULSE: f32 = 30.0;
+
+/// In game, connected to the server.
+pub struct PlayingState {
+    debug_text: Handle<UiNode>,
+    pointy: LogicalPosition<f64>,
+    skybox_switcher: SkyboxSwitcher,
+    // Index into the manifest's skyboxes, cycled with F2.
+    skybox_index: usize,
+    // Last gamepad connect or disconnect, for the debug text.
+    gamepad_status: String,
+    // Who F7 switched to watching, for the debug text.
+    spectate_status: String,
+    touch_overlay: TouchOverlay,
+    // What is under the cursor, or in the middle of the screen while the pointer is locked.
+    pick: Option<Pick>,
+    // Tints the pick when a click would push it.
+    highlight: Highlight,
+}
+
+impl PlayingState {
+    pub fn new() -> Self {
+        Self {
+            debug_text: Handle::NONE,
+            pointy: LogicalPosition { x: 0.0, y: 0.0 },
+            skybox_switcher: SkyboxSwitcher::new(SKYBOX_FADE),
+            skybox_index: 0,
+            gamepad_status: String::new(),
+            spectate_status: String::new(),
+            touch_overlay: TouchOverlay::new(),
+            pick: None,
+            highlight: Highlight::default(),
+        }
+    }
+
+    fn next_skybox(&mut self, ctx: &mut GameContext) {
+        let count = ctx.manifest.skyboxes.len();
+        if count == 0 || ctx.scene.is_none() {
+            return;
+        }
+
+        self.skybox_index = (self.skybox_index + 1) % count;
+        if let Some(name) = ctx.manifest.skyboxes.keys().nth(self.skybox_index).cloned() {
+            self.skybox_switcher.switch_to(ctx, &name);
+        }
+    }
+
+    // Cycles through the levels in the manifest, starting after the current one.
+    fn next_level(&mut self, ctx: &mut GameContext) {
+        let levels = ctx
+            .manifest
+            .assets
+            .keys()
+            .filter(|id| id.starts_with("levels."))
+            .collect::<Vec<&String>>();
+        if levels.is_empty() {
+            return;
+        }
+
+        let next = levels
+            .iter()
+            .position(|id| **id == ctx.level)
+            .map_or(0, |index| (index + 1) % levels.len());
+        ctx.next_level = Some(levels[next].clone());
+    }
+
+    fn next_camera_mode(&self, ctx: &mut GameContext) {
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            let mode = game_scene.camera_mode().next();
+            game_scene.set_camera_mode(&mut ctx.engine, mode);
+        }
+    }
+
+    fn toggle_free_fly(&mut self, ctx: &mut GameContext) {
+        self.spectate_status.clear();
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            game_scene.toggle_free_fly(&mut ctx.engine);
+        }
+    }
+
+    fn spectate_next_player(&mut self, ctx: &mut GameContext) {
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            self.spectate_status = match game_scene.spectate_next_player(&mut ctx.engine) {
+                Some(client_id) => format!("watching player {}", client_id),
+                None if game_scene.players.len() > 1 => String::new(),
+                None => "nobody else to watch".to_string(),
+            };
+        }
+    }
+
+    fn zoom(&self, ctx: &mut GameContext, delta: MouseScrollDelta) {
+        let steps = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_ZOOM_STEP,
+        };
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            game_scene.orbit.zoom(steps);
+        }
+    }
+
+    fn update_pick(&mut self, ctx: &mut GameContext) {
+        let game_scene = match ctx.scene.as_ref() {
+            Some(game_scene) => game_scene,
+            None => return,
+        };
+        let scene = &ctx.engine.scenes[game_scene.scene];
+        let screen_size = Vector2::new(
+            ctx.screen_size.width as f32,
+            ctx.screen_size.height as f32,
+        );
+        self.pick = picking::pick_position(ctx)
+            .and_then(|position| picking::pick(scene, game_scene.camera, position, screen_size));
+
+        let highlighted = self
+            .pick
+            .filter(|pick| picking::is_movable(scene, pick))
+            .map_or(Handle::NONE, |pick| pick.node);
+        self.highlight
+            .set(&mut ctx.engine, game_scene.scene, highlighted);
+    }
+
+    // Knocks the pick away from the camera, if it can move.
+    fn push_pick(&self, ctx: &mut GameContext) {
+        if let (Some(game_scene), Some(pick)) = (ctx.scene.as_ref(), self.pick.as_ref()) {
+            let scene = &mut ctx.engine.scenes[game_scene.scene];
+            let camera = scene.graph[game_scene.camera].global_position();
+            let direction = (pick.point - camera)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::z);
+            picking::push(scene, pick, direction * PUSH_IMPULSE);
+        }
+    }
+
+    fn toggle_fullscreen(&self, ctx: &mut GameContext) {
+        let window = ctx.engine.get_window();
+        if ctx.pointer_lock.is_fullscreen() {
+            ctx.pointer_lock.exit_fullscreen(&*ctx.platform, window);
+        } else {
+            ctx.pointer_lock.request_fullscreen(&*ctx.platform, window);
+        }
+    }
+
+    fn handle_debug_actions(&mut self, ctx: &mut GameContext) {
+        if ctx.input.was_action_pressed(Action::NextSkybox) {
+            self.next_skybox(ctx);
+        }
+        if ctx.input.was_action_pressed(Action::NextLevel) {
+            self.next_level(ctx);
+        }
+        if ctx.input.was_action_pressed(Action::ToggleFullscreen) {
+            self.toggle_fullscreen(ctx);
+        }
+        if ctx.input.was_action_pressed(Action::NextCameraMode) {
+            self.next_camera_mode(ctx);
+        }
+        if ctx.input.was_action_pressed(Action::ToggleFreeFly) {
+            self.toggle_free_fly(ctx);
+        }
+        if ctx.input.was_action_pressed(Action::SpectateNextPlayer) {
+            self.spectate_next_player(ctx);
+        }
+    }
+}
+
+impl GameState for PlayingState {
+    fn enter(&mut self, ctx: &mut GameContext) {
+        self.debug_text =
+            TextBuilder::new(WidgetBuilder::new()).build(&mut ctx.engine.user_interface.build_ctx());
+
+        // Our own model, which the camera follows from now on.
+        let client_id = ctx.connection.as_ref().map(|connection| connection.client_id());
+        if let (Some(game_scene), Some(client_id)) = (ctx.scene.as_mut(), client_id) {
+            game_scene.local_player = Some(client_id);
+            game_scene.spawn_player(&mut ctx.engine, client_id);
+        }
+
+        ctx.pointer_lock
+            .request_lock(&*ctx.platform, ctx.engine.get_window());
+    }
+
+    fn exit(&mut self, ctx: &mut GameContext) {
+        remove_widget(ctx, self.debug_text);
+        self.touch_overlay.remove(ctx);
+        if let Some(game_scene) = ctx.scene.as_ref() {
+            self.highlight
+                .set(&mut ctx.engine, game_scene.scene, Handle::NONE);
+        }
+        if let Some(game_scene) = ctx.scene.as_mut() {
+            if let Some(client_id) = game_scene.local_player.take() {
+                game_scene.remove_player(&mut ctx.engine, client_id);
+            }
+        }
+        self.skybox_switcher.cancel(ctx);
+        // Menus need the cursor back.
+        ctx.pointer_lock
+            .exit_lock(&*ctx.platform, ctx.engine.get_window());
+    }
+
+    fn update(&mut self, ctx: &mut GameContext) -> Transition {
+        if let Some(ConnectionStatus::Closed(reason)) =
+            ctx.connection.as_ref().map(|connection| connection.status())
+        {
+            return Transition::Switch(Box::new(DisconnectedState::new(reason)));
+        }
+
+        // Wait for the pointer before playing, there is no looking around without it. Touch
+        // screens look by dragging instead.
+        if ctx.input.was_action_pressed(Action::Menu)
+            || (!ctx.pointer_lock.is_locked() && !ctx.input.touch.is_active())
+        {
+            return Transition::Push(Box::new(PausedState::new()));
+        }
+
+        self.handle_debug_actions(ctx);
+        self.skybox_switcher.update(ctx);
+
+        self.touch_overlay.update(ctx);
+
+        let (look_x, look_y) = ctx.input.look_vector();
+        let (drag_x, drag_y) = ctx.input.touch.look_delta();
+        let dt = ctx.clock.timestep();
+        if let Some(look) = ctx.scene.as_mut().and_then(|scene| scene.look_mut()) {
+            look.process_stick(look_x, look_y, dt, &ctx.config.look);
+            // Dragging looks around just like moving the mouse.
+            look.process_mouse_motion(drag_x, drag_y, &ctx.config.look);
+        }
+
+        self.update_pick(ctx);
+
+        for event in ctx.input.gamepads.events() {
+            self.gamepad_status = match event {
+                GamepadEvent::Connected(name) => format!("gamepad connected: {}", name),
+                GamepadEvent::Disconnected(name) => format!("gamepad disconnected: {}", name),
+            };
+        }
+
+        // Keys can be rebound, so the help is built from the bindings.
+        let key = |action| {
+            let names = ctx.input.bindings.key_names(action);
+            if names.is_empty() {
+                "(unbound)".to_string()
+            } else {
+                names.join("/")
+            }
+        };
+        let mut text = format!(
+            "{}, {}, {}, {}, {} and mouse to move around\n{} for the next skybox, {} for the next level, {} for full screen\n{} for the third-person camera, mouse wheel to zoom\n{} to fly around ({} and {} for up and down, {} for faster), {} to watch other players\nscreen size: {}, {}\npointy: {}, {}",
+            key(Action::MoveForward), key(Action::StrafeLeft), key(Action::MoveBackward),
+            key(Action::StrafeRight), key(Action::Jump),
+            key(Action::NextSkybox), key(Action::NextLevel), key(Action::ToggleFullscreen),
+            key(Action::NextCameraMode),
+            key(Action::ToggleFreeFly), key(Action::FlyUp), key(Action::FlyDown),
+            key(Action::FlyFast), key(Action::SpectateNextPlayer),
+            ctx.screen_size.width, ctx.screen_size.height,
+            self.pointy.x, self.pointy.y
+        );
+        if let Some(err) = self.skybox_switcher.last_error() {
+            text += &format!("\n{}", err);
+        }
+        if !self.gamepad_status.is_empty() {
+            text += &format!("\n{}", self.gamepad_status);
+        }
+        if !self.spectate_status.is_empty() {
+            text += &format!("\n{}", self.spectate_status);
+        }
+        if let (Some(pick), Some(game_scene)) = (self.pick.as_ref(), ctx.scene.as_ref()) {
+            let name = ctx.engine.scenes[game_scene.scene].graph[pick.node].name();
+            text += &format!(
+                "\nlooking at {} at ({:.1}, {:.1}, {:.1}), {:.1} m away",
+                name, pick.point.x, pick.point.y, pick.point.z, pick.distance
+            );
+        }
+        ctx.engine.user_interface.send_message(TextMessage::text(
+            self.debug_text,
+            MessageDirection::ToWidget,
+            text,
+        ));
+
+        Transition::None
+    }
+
+    fn handle_event(&mut self, ctx: &mut GameContext, event: &Event<()>) -> Transition {
+        if let Event::WindowEvent {
+            event: WindowEvent::MouseWheel { delta, .. },
+            ..
+        } = event
+        {
+            self.zoom(ctx, *delta);
+        }
+
+        // Clicks that lock the pointer don't push anything yet.
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                },
+            ..
+        } = event
+        {
+            if ctx.pointer_lock.is_locked() {
+                self.push_pick(ctx);
+            }
+        }
+
+        if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } = event
+        {
+            // Without the lock the cursor is just passing over the window.
+            if !ctx.pointer_lock.is_locked() {
+                return Transition::None;
+            }
+
+            self.pointy.x += delta.0;
+            self.pointy.y += delta.1;
+
+            if let Some(look) = ctx.scene.as_mut().and_then(|scene| scene.look_mut()) {
+                look.process_mouse_motion(delta.0, delta.1, &ctx.config.look);
+            }
+
+            if let Some(connection) = ctx.connection.as_ref() {
+                connection.send(&OutgoingMessage::CursorPosition {
+                    client_id: connection.client_id(),
+                    x: self.pointy.x,
+                    y: self.pointy.y,
+                });
+            }
+        }
+
+        Transition::None
+    }
+}
@@ -0,0 +1,117 @@
+// The order of things within one fixed time step. A replay only repeats a session if every step
+// handles its input in the same place, however many steps a frame runs, so nothing that reacts to
+// input may run once per frame instead of once per step.
+
+/// The phases of one fixed time step, see `run_steps`.
+pub trait FixedStep {
+    /// Input for step `tick`: replayed events and the polled state of the platform.
+    fn feed_input(&mut self, tick: u64);
+    /// Reacts to what the input did to the UI, e.g. a button click.
+    fn handle_ui_messages(&mut self);
+    /// Game logic.
+    fn update(&mut self, tick: u64);
+    /// Forgets this step's key presses and advances the engine.
+    fn end_step(&mut self);
+}
+
+/// Runs `steps` steps numbered from `first_tick`, as the clock handed them out for a frame.
+pub fn run_steps(step: &mut impl FixedStep, first_tick: u64, steps: u32) {
+    for tick in first_tick..first_tick + steps as u64 {
+        step.feed_input(tick);
+        step.handle_ui_messages();
+        step.update(tick);
+        step.end_step();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_bits::recording::{
+        InputLog, LoggedEvent, RecordedButton, RecordedEvent, Replay,
+    };
+
+    // Opens a menu when the mouse button is released, like a UI button would.
+    struct MenuGame {
+        replay: Replay,
+        tick: u64,
+        clicks: usize,
+        menu_opened_on: Option<u64>,
+    }
+
+    impl MenuGame {
+        fn new() -> Self {
+            let click = |tick, pressed| LoggedEvent {
+                tick,
+                event: RecordedEvent::MouseButton {
+                    button: RecordedButton::Left,
+                    pressed,
+                },
+            };
+            Self {
+                replay: Replay::new(InputLog {
+                    events: vec![click(4, true), click(5, false)],
+                }),
+                tick: 0,
+                clicks: 0,
+                menu_opened_on: None,
+            }
+        }
+    }
+
+    impl FixedStep for MenuGame {
+        fn feed_input(&mut self, tick: u64) {
+            self.tick = tick;
+            for event in self.replay.take_events(tick) {
+                if let RecordedEvent::MouseButton { pressed: false, .. } = event {
+                    self.clicks += 1;
+                }
+            }
+        }
+
+        fn handle_ui_messages(&mut self) {
+            while self.clicks > 0 {
+                self.clicks -= 1;
+                self.menu_opened_on.get_or_insert(self.tick);
+            }
+        }
+
+        fn update(&mut self, tick: u64) {
+            assert_eq!(tick, self.tick);
+        }
+
+        fn end_step(&mut self) {}
+    }
+
+    // Runs ticks 0 to 11 in frames of `frame_steps` steps.
+    fn play(frame_steps: &[u32]) -> MenuGame {
+        let mut game = MenuGame::new();
+        let mut first_tick = 0;
+        for steps in frame_steps {
+            run_steps(&mut game, first_tick, *steps);
+            first_tick += *steps as u64;
+        }
+        assert_eq!(first_tick, 12);
+        game
+    }
+
+    #[test]
+    fn replayed_click_lands_on_its_own_tick() {
+        // One step per frame, a slow machine and a sped up replay.
+        for frames in [vec![1; 12], vec![3, 0, 5, 4], vec![8, 4]].iter() {
+            let game = play(frames);
+            assert_eq!(game.menu_opened_on, Some(5), "frames {:?}", frames);
+            assert!(game.replay.is_finished());
+        }
+    }
+
+    #[test]
+    fn frames_without_steps_handle_nothing() {
+        let mut game = MenuGame::new();
+        run_steps(&mut game, 0, 5);
+        run_steps(&mut game, 5, 0);
+        assert_eq!(game.menu_opened_on, None);
+        run_steps(&mut game, 5, 1);
+        assert_eq!(game.menu_opened_on, Some(5));
+    }
+}
@@ -0,0 +1,446 @@
+// Touch controls for phones and tablets. A thumb on the left half of the screen is a virtual
+// joystick centered where it landed, dragging on the right half looks around, and the button in
+// the bottom right corner jumps. The controls only show up once the screen has been touched, so
+// they stay out of the way with a mouse.
+
+use rg3d::{
+    core::{algebra::Vector2, color::Color, pool::Handle},
+    event::TouchPhase,
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        message::{MessageDirection, WidgetMessage},
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        HorizontalAlignment, VerticalAlignment,
+    },
+};
+
+use super::app::{GameContext, UiNode};
+
+// In pixels. How far the knob goes from the center of the stick.
+const STICK_RADIUS: f32 = 60.0;
+const KNOB_SIZE: f32 = 50.0;
+const JUMP_BUTTON_SIZE: f32 = 90.0;
+const JUMP_BUTTON_MARGIN: f32 = 30.0;
+
+/// A touch as the platform reports it, in physical pixels like the window size.
+#[derive(Clone, Copy, Debug)]
+pub struct TouchEvent {
+    pub id: u64,
+    pub phase: TouchPhase,
+    pub x: f32,
+    pub y: f32,
+}
+
+struct StickTouch {
+    id: u64,
+    center: Vector2<f32>,
+    position: Vector2<f32>,
+}
+
+pub struct TouchInput {
+    screen_width: f32,
+    screen_height: f32,
+    // Any touch so far, the controls stay hidden until then.
+    active: bool,
+    stick: Option<StickTouch>,
+    // The look touch and where it was last.
+    look: Option<(u64, Vector2<f32>)>,
+    look_delta: Vector2<f32>,
+    jump: Option<u64>,
+    jump_pressed: bool,
+}
+
+impl Default for TouchInput {
+    fn default() -> Self {
+        Self {
+            screen_width: 0.0,
+            screen_height: 0.0,
+            active: false,
+            stick: None,
+            look: None,
+            look_delta: Vector2::zeros(),
+            jump: None,
+            jump_pressed: false,
+        }
+    }
+}
+
+impl TouchInput {
+    pub fn set_screen_size(&mut self, width: u32, height: u32) {
+        self.screen_width = width as f32;
+        self.screen_height = height as f32;
+    }
+
+    /// Top left corner of the jump button, which is square.
+    fn jump_button_position(&self) -> Vector2<f32> {
+        let offset = JUMP_BUTTON_SIZE + JUMP_BUTTON_MARGIN;
+        Vector2::new(self.screen_width - offset, self.screen_height - offset)
+    }
+
+    fn is_on_jump_button(&self, position: Vector2<f32>) -> bool {
+        let corner = self.jump_button_position();
+        (corner.x..corner.x + JUMP_BUTTON_SIZE).contains(&position.x)
+            && (corner.y..corner.y + JUMP_BUTTON_SIZE).contains(&position.y)
+    }
+
+    pub fn handle(&mut self, event: TouchEvent) {
+        self.active = true;
+        let position = Vector2::new(event.x, event.y);
+
+        match event.phase {
+            TouchPhase::Started => {
+                if self.is_on_jump_button(position) {
+                    if self.jump.is_none() {
+                        self.jump_pressed = true;
+                    }
+                    self.jump = Some(event.id);
+                } else if position.x < self.screen_width * 0.5 {
+                    self.stick = Some(StickTouch {
+                        id: event.id,
+                        center: position,
+                        position,
+                    });
+                } else {
+                    self.look = Some((event.id, position));
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(stick) = self.stick.as_mut().filter(|stick| stick.id == event.id) {
+                    stick.position = position;
+                }
+                if let Some((_, last)) = self.look.as_mut().filter(|(id, _)| *id == event.id) {
+                    self.look_delta += position - *last;
+                    *last = position;
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self
+                    .stick
+                    .as_ref()
+                    .map_or(false, |stick| stick.id == event.id)
+                {
+                    self.stick = None;
+                }
+                if self.look.map_or(false, |(id, _)| id == event.id) {
+                    self.look = None;
+                }
+                if self.jump == Some(event.id) {
+                    self.jump = None;
+                }
+            }
+        }
+    }
+
+    /// Call at the end of each time step.
+    pub fn end_step(&mut self) {
+        self.jump_pressed = false;
+        self.look_delta = Vector2::zeros();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Tilt of the virtual joystick, -1 to 1 on both axes with down being positive.
+    pub fn stick(&self) -> (f32, f32) {
+        match self.stick.as_ref() {
+            Some(stick) => {
+                let offset = stick.position - stick.center;
+                let offset = offset / offset.norm().max(STICK_RADIUS);
+                (offset.x, offset.y)
+            }
+            None => (0.0, 0.0),
+        }
+    }
+
+    /// Pixels dragged in the look region during this time step.
+    pub fn look_delta(&self) -> (f64, f64) {
+        (self.look_delta.x as f64, self.look_delta.y as f64)
+    }
+
+    pub fn is_jump_down(&self) -> bool {
+        self.jump.is_some()
+    }
+
+    pub fn was_jump_pressed(&self) -> bool {
+        self.jump_pressed
+    }
+}
+
+fn translucent(alpha: u8) -> Brush {
+    Brush::Solid(Color::from_rgba(255, 255, 255, alpha))
+}
+
+/// Draws the touch controls, owned by the state that uses them.
+pub struct TouchOverlay {
+    // Screen size the widgets were laid out for.
+    built_for: (u32, u32),
+    look_region: Handle<UiNode>,
+    stick_base: Handle<UiNode>,
+    stick_knob: Handle<UiNode>,
+    jump_button: Handle<UiNode>,
+}
+
+impl TouchOverlay {
+    pub fn new() -> Self {
+        Self {
+            built_for: (0, 0),
+            look_region: Handle::NONE,
+            stick_base: Handle::NONE,
+            stick_knob: Handle::NONE,
+            jump_button: Handle::NONE,
+        }
+    }
+
+    fn build(&mut self, ctx: &mut GameContext) {
+        self.built_for = (ctx.screen_size.width, ctx.screen_size.height);
+        let width = ctx.screen_size.width as f32;
+        let height = ctx.screen_size.height as f32;
+        let jump_button_position = ctx.input.touch.jump_button_position();
+        let build_ctx = &mut ctx.engine.user_interface.build_ctx();
+
+        self.look_region = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_desired_position(Vector2::new(width * 0.5, 0.0))
+                .with_width(width * 0.5)
+                .with_height(height)
+                .with_background(translucent(10)),
+        )
+        .build(build_ctx);
+
+        self.stick_base = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(STICK_RADIUS * 2.0)
+                .with_height(STICK_RADIUS * 2.0)
+                .with_visibility(false)
+                .with_background(translucent(40)),
+        )
+        .build(build_ctx);
+
+        self.stick_knob = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(KNOB_SIZE)
+                .with_height(KNOB_SIZE)
+                .with_visibility(false)
+                .with_background(translucent(120)),
+        )
+        .build(build_ctx);
+
+        let label = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_horizontal_alignment(HorizontalAlignment::Center)
+                .with_vertical_alignment(VerticalAlignment::Center),
+        )
+        .with_text("Jump")
+        .build(build_ctx);
+        self.jump_button = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_desired_position(jump_button_position)
+                .with_width(JUMP_BUTTON_SIZE)
+                .with_height(JUMP_BUTTON_SIZE)
+                .with_background(translucent(60))
+                .with_child(label),
+        )
+        .build(build_ctx);
+    }
+
+    fn place(ctx: &mut GameContext, widget: Handle<UiNode>, center: Vector2<f32>, size: f32) {
+        ctx.engine
+            .user_interface
+            .send_message(WidgetMessage::desired_position(
+                widget,
+                MessageDirection::ToWidget,
+                center - Vector2::new(size * 0.5, size * 0.5),
+            ));
+    }
+
+    fn show(ctx: &mut GameContext, widget: Handle<UiNode>, visible: bool) {
+        ctx.engine
+            .user_interface
+            .send_message(WidgetMessage::visibility(
+                widget,
+                MessageDirection::ToWidget,
+                visible,
+            ));
+    }
+
+    /// Call once per time step.
+    pub fn update(&mut self, ctx: &mut GameContext) {
+        if !ctx.input.touch.is_active() {
+            return;
+        }
+        let screen_size = (ctx.screen_size.width, ctx.screen_size.height);
+        if self.look_region.is_some() && self.built_for != screen_size {
+            self.remove(ctx);
+        }
+        if self.look_region.is_none() {
+            self.build(ctx);
+        }
+
+        let stick = ctx
+            .input
+            .touch
+            .stick
+            .as_ref()
+            .map(|stick| (stick.center, stick.position));
+        match stick {
+            Some((center, position)) => {
+                // The knob stays on the rim when the thumb goes further.
+                let offset = position - center;
+                let knob = center + offset / offset.norm().max(STICK_RADIUS) * STICK_RADIUS;
+                Self::place(ctx, self.stick_base, center, STICK_RADIUS * 2.0);
+                Self::place(ctx, self.stick_knob, knob, KNOB_SIZE);
+                Self::show(ctx, self.stick_base, true);
+                Self::show(ctx, self.stick_knob, true);
+            }
+            None => {
+                Self::show(ctx, self.stick_base, false);
+                Self::show(ctx, self.stick_knob, false);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, ctx: &mut GameContext) {
+        for widget in [
+            self.look_region,
+            self.stick_base,
+            self.stick_knob,
+            self.jump_button,
+        ]
+        .iter()
+        {
+            if widget.is_some() {
+                ctx.engine
+                    .user_interface
+                    .send_message(WidgetMessage::remove(*widget, MessageDirection::ToWidget));
+            }
+        }
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(input: &mut TouchInput, id: u64, phase: TouchPhase, x: f32, y: f32) {
+        input.handle(TouchEvent { id, phase, x, y });
+    }
+
+    // The stick's radius is 60 pixels.
+    fn screen() -> TouchInput {
+        let mut input = TouchInput::default();
+        input.set_screen_size(800, 600);
+        input
+    }
+
+    #[test]
+    fn first_touch_activates() {
+        let mut input = screen();
+        assert!(!input.is_active());
+        touch(&mut input, 1, TouchPhase::Started, 100.0, 300.0);
+        assert!(input.is_active());
+
+        // And the controls stay up after the finger is lifted.
+        touch(&mut input, 1, TouchPhase::Ended, 100.0, 300.0);
+        assert!(input.is_active());
+    }
+
+    #[test]
+    fn left_half_is_a_joystick() {
+        let mut input = screen();
+        touch(&mut input, 1, TouchPhase::Started, 100.0, 300.0);
+        assert_eq!(input.stick(), (0.0, 0.0));
+
+        // Half the radius to the right.
+        touch(&mut input, 1, TouchPhase::Moved, 130.0, 300.0);
+        assert_eq!(input.stick(), (0.5, 0.0));
+
+        // Further than the radius is full tilt, into the other half of the screen too.
+        touch(&mut input, 1, TouchPhase::Moved, 100.0, 540.0);
+        assert_eq!(input.stick(), (0.0, 1.0));
+        touch(&mut input, 1, TouchPhase::Moved, 700.0, 300.0);
+        assert_eq!(input.stick(), (1.0, 0.0));
+        assert_eq!(input.look_delta(), (0.0, 0.0));
+
+        touch(&mut input, 1, TouchPhase::Ended, 700.0, 300.0);
+        assert_eq!(input.stick(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn right_half_looks_around() {
+        let mut input = screen();
+        touch(&mut input, 2, TouchPhase::Started, 500.0, 200.0);
+        touch(&mut input, 2, TouchPhase::Moved, 510.0, 195.0);
+        touch(&mut input, 2, TouchPhase::Moved, 530.0, 190.0);
+        assert_eq!(input.look_delta(), (30.0, -10.0));
+        assert_eq!(input.stick(), (0.0, 0.0));
+
+        // The delta is per time step.
+        input.end_step();
+        assert_eq!(input.look_delta(), (0.0, 0.0));
+        touch(&mut input, 2, TouchPhase::Moved, 525.0, 190.0);
+        assert_eq!(input.look_delta(), (-5.0, 0.0));
+
+        input.end_step();
+        touch(&mut input, 2, TouchPhase::Cancelled, 525.0, 190.0);
+        touch(&mut input, 2, TouchPhase::Moved, 600.0, 190.0);
+        assert_eq!(input.look_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn stick_and_look_at_the_same_time() {
+        let mut input = screen();
+        touch(&mut input, 1, TouchPhase::Started, 100.0, 300.0);
+        touch(&mut input, 2, TouchPhase::Started, 500.0, 200.0);
+        touch(&mut input, 1, TouchPhase::Moved, 100.0, 240.0);
+        touch(&mut input, 2, TouchPhase::Moved, 520.0, 200.0);
+        assert_eq!(input.stick(), (0.0, -1.0));
+        assert_eq!(input.look_delta(), (20.0, 0.0));
+
+        // Lifting one finger leaves the other alone.
+        touch(&mut input, 2, TouchPhase::Ended, 520.0, 200.0);
+        assert_eq!(input.stick(), (0.0, -1.0));
+    }
+
+    #[test]
+    fn jump_button_presses_once() {
+        let mut input = screen();
+        // Bottom right corner, inside the button.
+        touch(&mut input, 3, TouchPhase::Started, 720.0, 520.0);
+        assert!(input.is_jump_down());
+        assert!(input.was_jump_pressed());
+        // Not a look touch.
+        touch(&mut input, 3, TouchPhase::Moved, 730.0, 520.0);
+        assert_eq!(input.look_delta(), (0.0, 0.0));
+
+        input.end_step();
+        assert!(input.is_jump_down());
+        assert!(!input.was_jump_pressed());
+
+        touch(&mut input, 3, TouchPhase::Ended, 730.0, 520.0);
+        assert!(!input.is_jump_down());
+    }
+
+    #[test]
+    fn second_finger_on_jump_is_not_another_press() {
+        let mut input = screen();
+        touch(&mut input, 3, TouchPhase::Started, 720.0, 520.0);
+        input.end_step();
+        touch(&mut input, 4, TouchPhase::Started, 740.0, 540.0);
+        assert!(!input.was_jump_pressed());
+    }
+
+    #[test]
+    fn unknown_touches_are_ignored() {
+        let mut input = screen();
+        touch(&mut input, 1, TouchPhase::Started, 100.0, 300.0);
+        touch(&mut input, 9, TouchPhase::Moved, 150.0, 300.0);
+        touch(&mut input, 9, TouchPhase::Ended, 150.0, 300.0);
+        assert_eq!(input.stick(), (0.0, 0.0));
+        touch(&mut input, 1, TouchPhase::Moved, 160.0, 300.0);
+        assert_eq!(input.stick(), (1.0, 0.0));
+    }
+}
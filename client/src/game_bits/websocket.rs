@@ -2,9 +2,17 @@
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{ErrorEvent, MessageEvent, WebSocket};
+use web_sys::{CloseEvent, ErrorEvent, MessageEvent, WebSocket};
 use serde_json::json;
-use serde::{Serialize, Deserialize};
+use serde::Serialize;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+use super::{
+    net::{ConnectionStatus, Transport},
+    protocol::{self, IncomingMessage, OutgoingMessage},
+};
 
 macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
@@ -16,28 +24,22 @@ extern "C" {
     fn log(s: &str);
 }
 
-#[derive(Serialize, Deserialize)]
-struct IncomingMessage {
-    MessageType: String,
-    ClientId: u32,
-}
-
-#[derive(Serialize, Deserialize)]
-struct WelcomeMessage {
-    ClientId: u32,
-}
-
 pub struct Websocket {
     pub ws: WebSocket,
-    pub client_id: u32,
+    // Shared with the socket callbacks, which fill them in as the handshake progresses.
+    client_id: Rc<Cell<u32>>,
+    status: Rc<RefCell<ConnectionStatus>>,
 }
 
 impl Websocket {
 
-pub fn new() -> Websocket {
+pub fn new(url: &str) -> Websocket {
+    let client_id = Rc::new(Cell::new(0));
+    let status = Rc::new(RefCell::new(ConnectionStatus::Connecting));
     Websocket {
-        ws: Websocket::start(),
-        client_id: 10,
+        ws: Websocket::start(url, client_id.clone(), status.clone()),
+        client_id,
+        status,
     }
 }
 
@@ -54,12 +56,17 @@ where
     }
 }
 
-pub fn start() -> WebSocket {
-    // Connect to an echo server
-    let ws = Some(WebSocket::new("ws://localhost:5000/websocket").unwrap());
+pub fn start(
+    url: &str,
+    client_id: Rc<Cell<u32>>,
+    status: Rc<RefCell<ConnectionStatus>>,
+) -> WebSocket {
+    // Connect to the game server
+    let ws = Some(WebSocket::new(url).unwrap());
     // For small binary messages, like CBOR, Arraybuffer is more efficient than Blob handling
     ws.as_ref().unwrap().set_binary_type(web_sys::BinaryType::Arraybuffer);
     let cloned_ws = ws.as_ref().unwrap().clone();
+    let message_status = status.clone();
     // create callback
     let onmessage_callback = Closure::wrap(Box::new(move |e: MessageEvent| {
         // Handle difference Text/Binary,...
@@ -90,17 +97,22 @@ pub fn start() -> WebSocket {
             console_log!("message event, received Text: {:?}", txt);
             
             let txt_as_string: String = txt.into();
-            let payload: IncomingMessage = serde_json::from_str(&txt_as_string).unwrap();
+            let payload = match IncomingMessage::from_json(&txt_as_string) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    console_log!("bad message from server: {} ({})", txt_as_string, err);
+                    return;
+                }
+            };
 
-            if payload.MessageType == "welcome" {
-                //let payload: WelcomeMessage = serde_json::from_str(&txt_as_string).unwrap();
-                console_log!("welcome received! we are id {}", payload.ClientId);
+            if payload.is_welcome() {
+                console_log!("welcome received! we are id {}", payload.client_id);
+                client_id.set(payload.client_id);
+                *message_status.borrow_mut() = ConnectionStatus::Connected;
+            }
 
-                let ack = json!({
-                    "messageType": "ack",
-                    "clientId": 12,
-                });
-                match cloned_ws.send_with_str(&ack.to_string()) {
+            if let Some(reply) = protocol::reply_to(&payload) {
+                match cloned_ws.send_with_str(&reply.to_json()) {
                     Ok(_) => {},
                     //TODO do something with error
                     Err(err) => {}
@@ -115,20 +127,32 @@ pub fn start() -> WebSocket {
     // forget the callback to keep it alive
     onmessage_callback.forget();
 
+    let error_status = status.clone();
     let onerror_callback = Closure::wrap(Box::new(move |e: ErrorEvent| {
         console_log!("error event: {:?}", e);
+        *error_status.borrow_mut() = ConnectionStatus::Closed("connection error".to_string());
     }) as Box<dyn FnMut(ErrorEvent)>);
     ws.as_ref().unwrap().set_onerror(Some(onerror_callback.as_ref().unchecked_ref()));
     onerror_callback.forget();
 
+    let onclose_callback = Closure::wrap(Box::new(move |e: CloseEvent| {
+        console_log!("socket closed: {} {}", e.code(), e.reason());
+        let mut status = status.borrow_mut();
+        // An error is followed by a close, keep the more useful reason.
+        if let ConnectionStatus::Closed(_) = *status {
+            return;
+        }
+        *status = ConnectionStatus::Closed(format!("server closed the connection ({})", e.code()));
+    }) as Box<dyn FnMut(CloseEvent)>);
+    ws.as_ref().unwrap().set_onclose(Some(onclose_callback.as_ref().unchecked_ref()));
+    onclose_callback.forget();
+
     let another_cloned_ws = ws.as_ref().unwrap().clone();
     let onopen_callback = Closure::wrap(Box::new(move |_| {
         console_log!("socket opened");
 
-        let hello = json!({
-            "messageType": "salutations",
-        });
-        match another_cloned_ws.send_with_str(&hello.to_string()) {
+        let hello = OutgoingMessage::Salutations;
+        match another_cloned_ws.send_with_str(&hello.to_json()) {
             Ok(_) => {},
             //TODO do something with error
             Err(err) => {}
@@ -138,4 +162,30 @@ pub fn start() -> WebSocket {
     onopen_callback.forget();
     ws.unwrap()
 }
-}
\ No newline at end of file
+}
+
+impl Transport for Websocket {
+    fn send(&self, message: &OutgoingMessage) {
+        self.send_message(message);
+    }
+
+    fn client_id(&self) -> u32 {
+        self.client_id.get()
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.status.borrow().clone()
+    }
+}
+
+impl Drop for Websocket {
+    fn drop(&mut self) {
+        // The callbacks are leaked on purpose, detach them so they don't fire for a socket
+        // nobody is looking at anymore.
+        self.ws.set_onmessage(None);
+        self.ws.set_onerror(None);
+        self.ws.set_onclose(None);
+        self.ws.set_onopen(None);
+        let _ = self.ws.close();
+    }
+}
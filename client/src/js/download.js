@@ -0,0 +1,11 @@
+// Hands text to the user as a file download, e.g. input recordings for bug reports.
+
+export function downloadFile(name, contents) {
+    const url = URL.createObjectURL(new Blob([contents], { type: "application/json" }));
+    const link = document.createElement("a");
+    link.href = url;
+    link.download = name;
+    link.click();
+    // Some browsers only start the download after the click handler returned.
+    setTimeout(() => URL.revokeObjectURL(url), 0);
+}
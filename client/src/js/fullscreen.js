@@ -1,20 +1,66 @@
-export function addClickForFullscreen() {
-    let canvas = document.getElementsByTagName("canvas")[0];
-    canvas.addEventListener("click", makeFullscreen);
+// Pointer lock and fullscreen for the game, which decides when it wants them. Browsers only grant
+// either during a click, so a request that comes at any other time is remembered and tried again
+// on the next click on the canvas.
+
+let wantPointerLock = false;
+let wantFullscreen = false;
+
+function canvas() {
+    return document.querySelector("canvas");
+}
+
+function applyRequests() {
+    const element = canvas();
+    // Phones without the Fullscreen API, e.g. iPhones, just play in the page.
+    if (wantFullscreen && !document.fullscreenElement && element.requestFullscreen) {
+        element.requestFullscreen().catch(() => {});
+    }
+    if (wantPointerLock && document.pointerLockElement !== element && element.requestPointerLock) {
+        // Newer browsers return a promise, which is rejected outside of a click.
+        const result = element.requestPointerLock();
+        if (result) {
+            result.catch(() => {});
+        }
+    }
 }
 
-function makeFullscreen() {
-    let canvas = document.querySelector("canvas");
-    let cursorType = canvas.style.cursor;
-    canvas.requestFullscreen().then(() => {
-        canvas.requestPointerLock();
-        canvas.style.cursor = "none"; 
-
-        canvas.addEventListener("fullscreenchange", () => {
-            if( !document.fullscreenElement ) {
-                document.exitPointerLock();
-                canvas.style.cursor = cursorType;
-            }
-        });
+export function addPointerLockListeners() {
+    canvas().addEventListener("click", applyRequests);
+    document.addEventListener("pointerlockchange", () => {
+        // Escape unlocks, don't take the pointer back on the next click unless asked again.
+        if (document.pointerLockElement !== canvas()) {
+            wantPointerLock = false;
+        }
     });
-}
\ No newline at end of file
+    document.addEventListener("fullscreenchange", () => {
+        if (!document.fullscreenElement) {
+            wantFullscreen = false;
+        }
+    });
+}
+
+export function setPointerLock(locked) {
+    wantPointerLock = locked;
+    if (locked) {
+        applyRequests();
+    } else if (document.pointerLockElement) {
+        document.exitPointerLock();
+    }
+}
+
+export function isPointerLocked() {
+    return document.pointerLockElement === canvas();
+}
+
+export function setFullscreen(fullscreen) {
+    wantFullscreen = fullscreen;
+    if (fullscreen) {
+        applyRequests();
+    } else if (document.fullscreenElement) {
+        document.exitFullscreen();
+    }
+}
+
+export function isFullscreen() {
+    return !!document.fullscreenElement;
+}
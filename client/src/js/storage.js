@@ -0,0 +1,21 @@
+// Settings that should survive a reload, e.g. the key bindings. localStorage throws when the
+// browser blocks storage, the game then just starts with its defaults every time.
+
+const PREFIX = "gorust.";
+
+export function loadSetting(key) {
+    try {
+        return window.localStorage.getItem(PREFIX + key);
+    } catch (e) {
+        console.warn("can't load setting " + key + ": " + e);
+        return null;
+    }
+}
+
+export function saveSetting(key, value) {
+    try {
+        window.localStorage.setItem(PREFIX + key, value);
+    } catch (e) {
+        console.warn("can't save setting " + key + ": " + e);
+    }
+}
@@ -0,0 +1,35 @@
+// Collects touches on the canvas for Rust to pick up once per frame, winit doesn't report them in
+// the browser. Listeners stay passive so taps still click buttons, touch-action keeps the page
+// from scrolling and zooming instead.
+
+const STARTED = 0;
+const MOVED = 1;
+const ENDED = 2;
+const CANCELLED = 3;
+
+// Flat list of phase, id, x, y for each touch.
+let pending = [];
+
+function record(phase) {
+    return (event) => {
+        const ratio = window.devicePixelRatio || 1;
+        for (const touch of event.changedTouches) {
+            pending.push(phase, touch.identifier, touch.clientX * ratio, touch.clientY * ratio);
+        }
+    };
+}
+
+export function addTouchListeners() {
+    const canvas = document.querySelector("canvas");
+    canvas.style.touchAction = "none";
+    canvas.addEventListener("touchstart", record(STARTED), { passive: true });
+    canvas.addEventListener("touchmove", record(MOVED), { passive: true });
+    canvas.addEventListener("touchend", record(ENDED), { passive: true });
+    canvas.addEventListener("touchcancel", record(CANCELLED), { passive: true });
+}
+
+export function takeTouchEvents() {
+    const events = new Float64Array(pending);
+    pending = [];
+    return events;
+}
@@ -0,0 +1,280 @@
+// Browser front end. Only the things that need wasm-bindgen or web-sys live here, the game itself
+// is in game_bits.
+
+use rg3d::{
+    core::wasm_bindgen::{self, prelude::*},
+    event::TouchPhase,
+    event_loop::EventLoop,
+    window::Window,
+};
+
+use std::panic;
+
+use crate::game_bits::{
+    self,
+    app::{self, App, AppConfig},
+    assets::MANIFEST_PATH,
+    camera::LookSettings,
+    gamepad::{GamepadSettings, GamepadState},
+    net::Transport,
+    platform::{BoxedFuture, Platform},
+    touch::TouchEvent,
+    websocket::Websocket,
+};
+
+const SERVER_URL: &str = "ws://localhost:5000/websocket";
+
+// Lets a page pick options, e.g. index.html?level=levels.withcamera&dev
+fn url_params() -> Option<web_sys::UrlSearchParams> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search).ok()
+}
+
+// e.g. index.html?sensitivity=0.1&invert_y
+fn look_settings(params: Option<&web_sys::UrlSearchParams>) -> LookSettings {
+    let mut settings = LookSettings::default();
+    if let Some(params) = params {
+        if let Some(sensitivity) = params.get("sensitivity").and_then(|s| s.parse().ok()) {
+            settings.sensitivity = sensitivity;
+        }
+        settings.invert_y = params.has("invert_y");
+    }
+    settings
+}
+
+// e.g. index.html?deadzone=0.25
+fn gamepad_settings(params: Option<&web_sys::UrlSearchParams>) -> GamepadSettings {
+    let mut settings = GamepadSettings::default();
+    if let Some(deadzone) = params
+        .and_then(|params| params.get("deadzone"))
+        .and_then(|deadzone| deadzone.parse().ok())
+    {
+        settings.deadzone = deadzone;
+    }
+    settings
+}
+
+// The Gamepad API hands out snapshots, so this has to be called again for every poll. Slots of
+// unplugged pads are null.
+fn poll_gamepads() -> Option<Vec<GamepadState>> {
+    let pads = web_sys::window()?.navigator().get_gamepads().ok()?;
+    let pads = pads
+        .iter()
+        .filter_map(|pad| pad.dyn_into::<web_sys::Gamepad>().ok())
+        .filter(|pad| pad.connected() && pad.mapping() == web_sys::GamepadMappingType::Standard)
+        .map(|pad| GamepadState {
+            index: pad.index(),
+            name: pad.id(),
+            axes: pad
+                .axes()
+                .iter()
+                .map(|axis| axis.as_f64().unwrap_or(0.0) as f32)
+                .collect(),
+            buttons: pad
+                .buttons()
+                .iter()
+                .filter_map(|button| button.dyn_into::<web_sys::GamepadButton>().ok())
+                .map(|button| button.value() as f32)
+                .collect(),
+        })
+        .collect();
+    Some(pads)
+}
+
+struct WebPlatform;
+
+impl Platform for WebPlatform {
+    fn spawn(&self, future: BoxedFuture) {
+        rg3d::core::wasm_bindgen_futures::spawn_local(future);
+    }
+
+    fn connect(&self, url: &str) -> Box<dyn Transport> {
+        Box::new(Websocket::new(url))
+    }
+
+    fn uncached_url(&self, url: &str) -> String {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{}{}t={}", url, separator, js_sys::Date::now() as u64)
+    }
+
+    fn load_setting(&self, key: &str) -> Option<String> {
+        loadSetting(key)
+    }
+
+    fn save_setting(&self, key: &str, value: &str) {
+        saveSetting(key, value);
+    }
+
+    fn gamepads(&self) -> Vec<GamepadState> {
+        poll_gamepads().unwrap_or_default()
+    }
+
+    fn touch_events(&self) -> Vec<TouchEvent> {
+        touch_events()
+    }
+
+    // winit can do these too, but fullscreen.js knows to wait for a click.
+    fn set_pointer_lock(&self, _window: &Window, locked: bool) {
+        setPointerLock(locked);
+    }
+
+    fn is_pointer_locked(&self) -> bool {
+        isPointerLocked()
+    }
+
+    fn set_fullscreen(&self, _window: &Window, fullscreen: bool) {
+        setFullscreen(fullscreen);
+    }
+
+    fn is_fullscreen(&self, _window: &Window) -> bool {
+        isFullscreen()
+    }
+
+    fn save_recording(&self, json: &str) {
+        downloadFile("recording.json", json);
+    }
+}
+
+#[wasm_bindgen(module = "/src/js/fullscreen.js")]
+extern {
+    fn addPointerLockListeners();
+    fn setPointerLock(locked: bool);
+    fn isPointerLocked() -> bool;
+    fn setFullscreen(fullscreen: bool);
+    fn isFullscreen() -> bool;
+}
+
+#[wasm_bindgen(module = "/src/js/download.js")]
+extern {
+    fn downloadFile(name: &str, contents: &str);
+}
+
+#[wasm_bindgen(module = "/src/js/touch.js")]
+extern {
+    fn addTouchListeners();
+    fn takeTouchEvents() -> Box<[f64]>;
+}
+
+// touch.js hands them over as a flat list of phase, id, x, y.
+fn touch_events() -> Vec<TouchEvent> {
+    takeTouchEvents()
+        .chunks_exact(4)
+        .map(|touch| TouchEvent {
+            phase: match touch[0] as u32 {
+                0 => TouchPhase::Started,
+                1 => TouchPhase::Moved,
+                2 => TouchPhase::Ended,
+                _ => TouchPhase::Cancelled,
+            },
+            id: touch[1] as u64,
+            x: touch[2] as f32,
+            y: touch[3] as f32,
+        })
+        .collect()
+}
+
+#[wasm_bindgen(module = "/src/js/storage.js")]
+extern {
+    fn loadSetting(key: &str) -> Option<String>;
+    fn saveSetting(key: &str, value: &str);
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn error(msg: String);
+
+    type Error;
+
+    #[wasm_bindgen(constructor)]
+    fn new() -> Error;
+
+    #[wasm_bindgen(structural, method, getter)]
+    fn stack(error: &Error) -> String;
+}
+
+fn hook_impl(info: &panic::PanicInfo) {
+    let mut msg = info.to_string();
+
+    // Add the error stack to our message.
+    //
+    // This ensures that even if the `console` implementation doesn't
+    // include stacks for `console.error`, the stack is still available
+    // for the user. Additionally, Firefox's console tries to clean up
+    // stack traces, and ruins Rust symbols in the process
+    // (https://bugzilla.mozilla.org/show_bug.cgi?id=1519569) but since
+    // it only touches the logged message's associated stack, and not
+    // the message's contents, by including the stack in the message
+    // contents we make sure it is available to the user.
+    msg.push_str("\n\nStack:\n\n");
+    let e = Error::new();
+    let stack = e.stack();
+    msg.push_str(&stack);
+
+    // Safari's devtools, on the other hand, _do_ mess with logged
+    // messages' contents, so we attempt to break their heuristics for
+    // doing that by appending some whitespace.
+    // https://github.com/rustwasm/console_error_panic_hook/issues/7
+    msg.push_str("\n\n");
+
+    // Finally, log the panic with `console.error`!
+    error(msg);
+}
+
+/// A panic hook for use with
+/// [`std::panic::set_hook`](https://doc.rust-lang.org/nightly/std/panic/fn.set_hook.html)
+/// that logs panics into
+/// [`console.error`](https://developer.mozilla.org/en-US/docs/Web/API/Console/error).
+///
+/// On non-wasm targets, prints the panic to `stderr`.
+pub fn hook(info: &panic::PanicInfo) {
+    hook_impl(info);
+}
+
+/// Set the `console.error` panic hook the first time this is called. Subsequent
+/// invocations do nothing.
+#[inline]
+pub fn set_once() {
+    use std::sync::Once;
+    static SET_HOOK: Once = Once::new();
+    SET_HOOK.call_once(|| {
+        panic::set_hook(Box::new(hook));
+    });
+}
+
+#[wasm_bindgen]
+pub fn main() {
+    set_once();
+
+    game_bits::js_channel::send("snac0".to_string());
+
+    // Create event loop that will be used to "listen" events from the OS.
+    let event_loop = EventLoop::new();
+    let engine = app::create_engine(&event_loop);
+    let params = url_params();
+    let app = App::new(
+        engine,
+        Box::new(WebPlatform),
+        AppConfig {
+            server_url: SERVER_URL.to_string(),
+            level: params.as_ref().and_then(|params| params.get("level")),
+            manifest_url: MANIFEST_PATH.to_string(),
+            hashed_asset_urls: true,
+            hot_reload: params.as_ref().map_or(false, |params| params.has("dev")),
+            look: look_settings(params.as_ref()),
+            gamepad: gamepad_settings(params.as_ref()),
+            record: params.as_ref().map_or(false, |params| params.has("record")),
+            // Replays are for the desktop build, which can read files.
+            replay: None,
+            exit_after_replay: false,
+            time_scale: 1.0,
+        },
+    );
+
+    unsafe {
+        addPointerLockListeners();
+        addTouchListeners();
+    }
+
+    app::run(event_loop, app);
+}